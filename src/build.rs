@@ -1,51 +1,205 @@
+mod archive;
+mod date;
 mod encrypt;
+mod facets;
 mod ingest_tagging_in_progress;
+mod ingest_times;
 mod library;
+mod locale;
+mod password_strength;
+mod playlists;
 mod render_pages;
 mod restrictions;
+mod rule_keys;
+mod search_index;
+mod secrets;
+mod staged_output;
 mod sync_build_videos;
+mod tag_expression;
+mod tag_stats;
 mod update_thumbnails;
+mod video_metadata;
+mod video_neighbors;
+mod video_statuses;
+mod web_assets;
 
+use crate::build::encrypt::{derive_file_key, derive_secret_name};
 use crate::build::ingest_tagging_in_progress::ingest_tagging_in_progress;
-use crate::build::library::create_library;
-use crate::build::render_pages::render_pages;
-use crate::build::restrictions::Restrictions;
+use crate::build::ingest_times::read_ingest_times;
+use crate::build::library::{create_library, VideoFiles};
+use crate::build::password_strength::check_password_strength;
+use crate::build::restrictions::Visibility;
+use crate::build::rule_keys::{update_rule_keys, RuleKey};
+use crate::build::secrets::resolve_passwords;
 use crate::build::sync_build_videos::sync_build_videos;
 use crate::build::update_thumbnails::update_thumbnails;
+use crate::build::video_metadata::update_video_metadata;
+use crate::build::video_statuses::update_video_statuses;
 use crate::config::Config;
 use crate::tags_file::TagsFile;
 use crate::utils::maybe_read_string;
+use aes_gcm::{Aes256Gcm, Key};
 use anyhow::Context;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::path::Path;
 
+pub use crate::build::date::Date;
+pub use crate::build::ingest_tagging_in_progress::ingest_tags;
+pub use crate::build::library::Library;
+#[cfg(test)]
+pub use crate::build::library::LibraryVideo;
+pub use crate::build::password_strength::{generate_code, password_entropy_bits};
+pub use crate::build::playlists::PLAYLISTS_DIR;
+pub use crate::build::render_pages::{clean_name, collection_page, render_pages, PageSelection};
+pub use crate::build::restrictions::{Restrictions, RuleState, VideoStatus};
+pub use crate::build::secrets::{resolve_password, SecretsFile};
+pub use crate::build::video_neighbors::video_neighbors;
+pub use crate::build::web_assets::{embedded_asset_data, embedded_asset_names, WEB_OVERRIDE_DIR};
+
+pub const ALL_TAGS_PATH: &str = "data/all_tags.txt";
+pub const RESTRICTIONS_PATH: &str = "data/restrictions.json";
+pub const TAGGING_IN_PROGRESS_DIR: &str = "data/tagging_in_progress";
+
 pub fn build(config: &Config) -> anyhow::Result<()> {
-    let all_tags_path = "data/all_tags.txt";
-    let mut all_tags: TagsFile = maybe_read_string(all_tags_path)?
-        .unwrap_or_default()
-        .parse()
-        .context("failed to parse data/tags.txt")?;
+    let mut all_tags = read_all_tags()?;
 
     log::info!("Read existing tags for {} videos", all_tags.videos.len());
     let ingest_result = ingest_tagging_in_progress(&mut all_tags);
 
     // In all cases, persist the new tags
-    let write_result = fs::write(all_tags_path, all_tags.to_string());
+    let write_result = fs::write(ALL_TAGS_PATH, all_tags.to_string());
     ingest_result?;
     write_result?;
 
-    sync_build_videos(Path::new("data/videos"), Path::new("build/videos"))?;
-
-    let thumbnails = update_thumbnails(config, Path::new("build/videos"), &all_tags.videos)?;
-
-    let restrictions = match maybe_read_string("data/restrictions.json")? {
-        None => Restrictions::default(),
-        Some(data) => serde_json::from_str(&data)?,
-    };
-    let library = create_library(config, &all_tags, &restrictions, &thumbnails)?;
+    let library = update_library(config, &all_tags)?;
 
     log::info!("Will render HTML pages");
-    render_pages(config, &library)?;
+    render_pages(config, &library, &PageSelection::All)?;
 
     Ok(())
 }
+
+pub fn read_all_tags() -> anyhow::Result<TagsFile> {
+    maybe_read_string(ALL_TAGS_PATH)?
+        .unwrap_or_default()
+        .parse()
+        .context("failed to parse data/all_tags.txt")
+}
+
+pub fn read_restrictions() -> anyhow::Result<Restrictions> {
+    match maybe_read_string(RESTRICTIONS_PATH)? {
+        None => Ok(Restrictions::default()),
+        Some(data) => serde_json::from_str(&data).context("failed to parse restrictions"),
+    }
+}
+
+/// Bring the build videos and thumbnails up to date and create the library that describes them
+pub fn update_library(config: &Config, all_tags: &TagsFile) -> anyhow::Result<Library> {
+    let restrictions = read_restrictions()?;
+    let passwords = resolve_passwords(config, &restrictions)?;
+    check_password_strength(&restrictions, &passwords, config.file_access_iterations)?;
+    let today = Date::today()?;
+    restrictions.warn_expiring(today, config.restriction_expiry_warning_days);
+    let statuses = update_video_statuses(all_tags, &restrictions, today)?;
+    let removed: BTreeSet<_> = statuses
+        .iter()
+        .filter(|(_, status)| **status == VideoStatus::Removed)
+        .map(|(video, _)| video.clone())
+        .collect();
+    if !removed.is_empty() {
+        log::info!("{} videos are not published", removed.len());
+    }
+
+    let rule_keys = update_rule_keys(config, &restrictions, &passwords)?;
+    let secrets = video_secrets(all_tags, &restrictions, &rule_keys, today)?;
+    sync_build_videos(
+        Path::new("data/videos"),
+        Path::new("build/videos"),
+        &secrets.file_keys,
+        &removed,
+    )?;
+
+    let published_videos: Vec<_> = all_tags
+        .videos
+        .iter()
+        .filter(|video| !removed.contains(&video.name))
+        .collect();
+    let thumbnails = update_thumbnails(
+        config,
+        Path::new("data/videos"),
+        &published_videos,
+        &secrets.thumbnail_names,
+    )?;
+
+    // The hidden videos show neither a preview nor their duration in the tag statistics
+    let visible_videos: Vec<_> = published_videos
+        .iter()
+        .copied()
+        .filter(|video| !secrets.thumbnail_names.contains_key(&video.name))
+        .collect();
+    let metadata = update_video_metadata(Path::new("data/videos"), &visible_videos)?;
+
+    create_library(
+        &published_videos,
+        &restrictions,
+        today,
+        &rule_keys,
+        &VideoFiles {
+            file_keys: &secrets.file_keys,
+            thumbnails: &thumbnails,
+            ingest_times: &read_ingest_times()?,
+            metadata: &metadata,
+        },
+    )
+}
+
+/// The secrets derived from the keys of the rules that grant access to each video
+#[derive(Debug, Default)]
+struct VideoSecrets {
+    /// The keys of the videos that should be stored encrypted in the build
+    file_keys: BTreeMap<String, Key<Aes256Gcm>>,
+    /// The thumbnail names of the hidden videos
+    thumbnail_names: BTreeMap<String, String>,
+}
+
+fn video_secrets(
+    all_tags: &TagsFile,
+    restrictions: &Restrictions,
+    rule_keys: &BTreeMap<String, RuleKey>,
+    today: Date,
+) -> anyhow::Result<VideoSecrets> {
+    let mut secrets = VideoSecrets::default();
+
+    for video in &all_tags.videos {
+        let rules = restrictions.find_all(video, today);
+        let keys_of_rules = rules
+            .iter()
+            .map(|rule| {
+                rule_keys
+                    .get(&rule.name)
+                    .map(|key| &key.content_key)
+                    .context("missing rule key")
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if rules.iter().any(|rule| rule.encrypt_videos) {
+            secrets.file_keys.insert(
+                video.name.clone(),
+                derive_file_key(&video.name, &keys_of_rules),
+            );
+        }
+
+        if rules
+            .iter()
+            .any(|rule| rule.visibility == Visibility::Hidden)
+        {
+            secrets.thumbnail_names.insert(
+                video.name.clone(),
+                derive_secret_name(&video.name, &keys_of_rules),
+            );
+        }
+    }
+
+    Ok(secrets)
+}
@@ -0,0 +1,134 @@
+use crate::build::date::Date;
+use crate::build::locale::Locale;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// The videos grouped by year, month and day, from the most recent
+#[derive(Debug, Serialize)]
+pub struct Archive<V> {
+    pub years: Vec<ArchiveYear<V>>,
+    /// The videos without a date tag
+    pub undated: Vec<V>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveYear<V> {
+    pub year: u16,
+    pub page: String,
+    pub count: usize,
+    pub months: Vec<ArchiveMonth<V>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveMonth<V> {
+    pub name: String,
+    pub page: String,
+    pub count: usize,
+    pub sessions: Vec<ArchiveSession<V>>,
+}
+
+/// The videos of a single day
+#[derive(Debug, Serialize)]
+pub struct ArchiveSession<V> {
+    pub name: String,
+    pub videos: Vec<V>,
+}
+
+/// Group the videos, naming the months and days in the language of the locale
+pub fn archive<V>(
+    videos: impl IntoIterator<Item = (Option<Date>, V)>,
+    locale: Locale,
+) -> Archive<V> {
+    let mut sessions = BTreeMap::<_, Vec<V>>::new();
+    let mut undated = Vec::new();
+    for (date, video) in videos {
+        match date {
+            None => undated.push(video),
+            Some(date) => sessions.entry(Reverse(date)).or_default().push(video),
+        }
+    }
+
+    let mut years: Vec<ArchiveYear<V>> = Vec::new();
+    for (Reverse(date), videos) in sessions {
+        if years.last().is_none_or(|year| year.year != date.year) {
+            years.push(ArchiveYear {
+                year: date.year,
+                page: format!("archive/{}.html", date.year),
+                count: 0,
+                months: Vec::new(),
+            });
+        }
+        let year = years.last_mut().unwrap();
+
+        let month_page = format!("archive/{}-{:02}.html", date.year, date.month);
+        if year
+            .months
+            .last()
+            .is_none_or(|month| month.page != month_page)
+        {
+            year.months.push(ArchiveMonth {
+                name: date.format_month(locale),
+                page: month_page,
+                count: 0,
+                sessions: Vec::new(),
+            });
+        }
+        let month = year.months.last_mut().unwrap();
+
+        year.count += videos.len();
+        month.count += videos.len();
+        month.sessions.push(ArchiveSession {
+            name: date.format(locale),
+            videos,
+        });
+    }
+
+    Archive { years, undated }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive() {
+        let date = |s: &str| Some(s.parse::<Date>().unwrap());
+        let archive = archive(
+            [
+                (date("2024-03-02"), "a"),
+                (None, "b"),
+                (date("2023-12-01"), "c"),
+                (date("2024-03-02"), "d"),
+                (date("2024-03-15"), "e"),
+            ],
+            Locale::Fr,
+        );
+
+        assert_eq!(archive.undated, vec!["b"]);
+        assert_eq!(
+            archive
+                .years
+                .iter()
+                .map(|year| (year.year, year.count))
+                .collect::<Vec<_>>(),
+            vec![(2024, 3), (2023, 1)]
+        );
+
+        let march = &archive.years[0].months[0];
+        assert_eq!(march.name, "mars 2024");
+        assert_eq!(march.page, "archive/2024-03.html");
+        assert_eq!(
+            march
+                .sessions
+                .iter()
+                .map(|session| (session.name.as_str(), session.videos.clone()))
+                .collect::<Vec<_>>(),
+            vec![("15 mars 2024", vec!["e"]), ("2 mars 2024", vec!["a", "d"])]
+        );
+        assert_eq!(
+            archive.years[1].months[0].sessions[0].name,
+            "1er décembre 2023"
+        );
+    }
+}
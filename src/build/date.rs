@@ -0,0 +1,190 @@
+use crate::build::locale::Locale;
+use anyhow::{ensure, Context};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// A calendar date, written as `YYYY-MM-DD` in the tags and in the restrictions
+#[derive(Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    pub fn today() -> anyhow::Result<Self> {
+        let seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        Ok(Self::from_days_since_epoch((seconds / 86_400) as i64))
+    }
+
+    /// Convert from the number of days since 1970-01-01, in the proleptic Gregorian calendar
+    pub fn from_days_since_epoch(days: i64) -> Self {
+        // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        Date {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+        }
+    }
+
+    /// The number of days since 1970-01-01, in the proleptic Gregorian calendar
+    pub fn days_since_epoch(self) -> i64 {
+        // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let month = i64::from(self.month);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+        let day_of_year = (153 * shifted_month + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    /// The date in the language of the locale, like "1er mars 2024" or "March 1, 2024"
+    pub fn format(self, locale: Locale) -> String {
+        let month = locale.month_name(self.month);
+        match locale {
+            Locale::Fr if self.day == 1 => format!("1er {} {}", month, self.year),
+            Locale::Fr => format!("{} {} {}", self.day, month, self.year),
+            Locale::En => format!("{} {}, {}", month, self.day, self.year),
+        }
+    }
+
+    /// The month of the date in the language of the locale, like "mars 2024" or "March 2024"
+    pub fn format_month(self, locale: Locale) -> String {
+        format!("{} {}", locale.month_name(self.month), self.year)
+    }
+}
+
+impl FromStr for Date {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is_well_formed = s.len() == 10
+            && s.bytes().enumerate().all(|(index, byte)| match index {
+                4 | 7 => byte == b'-',
+                _ => byte.is_ascii_digit(),
+            });
+        ensure!(is_well_formed, "invalid date {:?}, expected YYYY-MM-DD", s);
+
+        let mut parts = s.splitn(3, '-');
+        let mut next_part = |name: &str| {
+            parts
+                .next()
+                .with_context(|| format!("missing {} in date {:?}", name, s))
+        };
+
+        let date = Date {
+            year: next_part("year")?.parse().context("invalid year")?,
+            month: next_part("month")?.parse().context("invalid month")?,
+            day: next_part("day")?.parse().context("invalid day")?,
+        };
+        ensure!(
+            (1..=12).contains(&date.month)
+                && (1..=days_in_month(date.year, date.month)).contains(&date.day),
+            "invalid date {:?}, expected YYYY-MM-DD",
+            s
+        );
+
+        Ok(date)
+    }
+}
+
+/// The number of days of a month, from 1 for January
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl TryFrom<String> for Date {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Date> for String {
+    fn from(value: Date) -> Self {
+        value.to_string()
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_days_since_epoch() {
+        let cases = [
+            ("1970-01-01", 0),
+            ("2000-02-29", 11_016),
+            ("2024-03-01", 19_783),
+            ("2024-12-31", 20_088),
+        ];
+
+        for (date, days) in cases {
+            let date: Date = date.parse().unwrap();
+            assert_eq!(date.days_since_epoch(), days);
+            assert_eq!(Date::from_days_since_epoch(days), date);
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let date: Date = "2024-03-09".parse().unwrap();
+        assert_eq!(date.to_string(), "2024-03-09");
+
+        assert!("2024-13-09".parse::<Date>().is_err());
+        assert!("2024-02-29".parse::<Date>().is_ok());
+        assert!("2024-02-30".parse::<Date>().is_err());
+        assert!("2023-02-29".parse::<Date>().is_err());
+        assert!("1900-02-29".parse::<Date>().is_err());
+        assert!("2000-02-29".parse::<Date>().is_ok());
+        assert!("2024-04-31".parse::<Date>().is_err());
+        assert!("2024-03".parse::<Date>().is_err());
+        assert!("2024-3-09".parse::<Date>().is_err());
+        assert!("+024-03-09".parse::<Date>().is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        let first: Date = "2024-03-01".parse().unwrap();
+        let other: Date = "2023-12-15".parse().unwrap();
+
+        assert_eq!(first.format(Locale::Fr), "1er mars 2024");
+        assert_eq!(other.format(Locale::Fr), "15 décembre 2023");
+        assert_eq!(first.format(Locale::En), "March 1, 2024");
+        assert_eq!(other.format_month(Locale::En), "December 2023");
+    }
+}
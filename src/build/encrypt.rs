@@ -1,24 +1,32 @@
+use aes_gcm::aead::rand_core::RngCore;
 use aes_gcm::aead::{Aead, Nonce, OsRng};
 use aes_gcm::{AeadCore, Aes256Gcm, Key, KeyInit};
 use anyhow::{anyhow, Context};
 use pbkdf2::pbkdf2_hmac;
-use sha2::Sha256;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
 
-#[derive(Debug)]
+/// Size of the plaintext chunks of an encrypted file. Each chunk is sealed on its own, so that the
+/// browser can decrypt the file while it is downloaded
+pub const FILE_CHUNK_SIZE: usize = 1024 * 1024;
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Encrypted {
     pub iv: String,
     pub ciphertext: String,
 }
 
-pub fn encrypt(
-    password: &str,
-    salt: &str,
-    iterations: u32,
-    plaintext: &str,
-) -> anyhow::Result<Encrypted> {
-    let key = derive_key(password, salt, iterations);
-    let cipher = Aes256Gcm::new(&key);
-    let iv = Aes256Gcm::generate_nonce(&mut OsRng);
+/// Encrypt with an IV derived from the key and the plaintext, so that the output is the same on every
+/// build while the inputs are unchanged. This only reveals whether two ciphertexts of the same key
+/// hold the same plaintext.
+pub fn encrypt(key: &Key<Aes256Gcm>, plaintext: &str) -> anyhow::Result<Encrypted> {
+    let cipher = Aes256Gcm::new(key);
+    let iv = synthetic_iv(key, plaintext.as_bytes());
     let ciphertext = cipher.encrypt(&iv, plaintext.as_bytes())?;
 
     Ok(Encrypted {
@@ -27,15 +35,8 @@ pub fn encrypt(
     })
 }
 
-#[allow(unused)]
-pub fn decrypt(
-    password: &str,
-    salt: &str,
-    iterations: u32,
-    encrypted: &Encrypted,
-) -> anyhow::Result<String> {
-    let key = derive_key(password, salt, iterations);
-    let cipher = Aes256Gcm::new(&key);
+pub fn decrypt(key: &Key<Aes256Gcm>, encrypted: &Encrypted) -> anyhow::Result<String> {
+    let cipher = Aes256Gcm::new(key);
     let iv = base16ct::lower::decode_vec(&encrypted.iv)?;
     let iv: [u8; 12] = iv.try_into().map_err(|_| anyhow!("invalid IV"))?;
     let iv: Nonce<Aes256Gcm> = iv.into();
@@ -47,21 +48,266 @@ pub fn decrypt(
     String::from_utf8(plaintext).context("invalid utf-8")
 }
 
-fn derive_key(password: &str, salt: &str, iterations: u32) -> Key<Aes256Gcm> {
+fn synthetic_iv(key: &Key<Aes256Gcm>, plaintext: &[u8]) -> Nonce<Aes256Gcm> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"lindy-hop iv\0");
+    hasher.update(key);
+    hasher.update(plaintext);
+    let hash = hasher.finalize();
+    *Nonce::<Aes256Gcm>::from_slice(&hash[0..NONCE_SIZE])
+}
+
+/// Encrypt a whole file with [`encrypt_stream()`]
+pub fn encrypt_file(key: &Key<Aes256Gcm>, source: &Path, destination: &Path) -> anyhow::Result<()> {
+    let reader = BufReader::new(File::open(source)?);
+    let mut writer = BufWriter::new(File::create(destination)?);
+    encrypt_stream(key, reader, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Encrypt a stream in chunks of [`FILE_CHUNK_SIZE`]. The output starts with a random base nonce,
+/// followed by the sealed chunks. The nonce of each chunk mixes in its index and whether it is the
+/// last one, so that chunks cannot be reordered and the file cannot be truncated without detection.
+pub fn encrypt_stream(
+    key: &Key<Aes256Gcm>,
+    mut reader: impl Read,
+    mut writer: impl Write,
+) -> anyhow::Result<()> {
+    let cipher = Aes256Gcm::new(key);
+    let base_nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    writer.write_all(&base_nonce)?;
+
+    let mut chunk = read_chunk(&mut reader, FILE_CHUNK_SIZE)?;
+    for index in 0.. {
+        let next_chunk = read_chunk(&mut reader, FILE_CHUNK_SIZE)?;
+        let is_last = next_chunk.is_empty();
+        let nonce = chunk_nonce(&base_nonce, index, is_last);
+        writer.write_all(&cipher.encrypt(&nonce, chunk.as_slice())?)?;
+
+        if is_last {
+            break;
+        }
+        chunk = next_chunk;
+    }
+
+    Ok(())
+}
+
+/// Decrypt a whole file produced by [`encrypt_file()`]
+#[allow(unused)]
+pub fn decrypt_file(key: &Key<Aes256Gcm>, source: &Path, destination: &Path) -> anyhow::Result<()> {
+    let reader = BufReader::new(File::open(source)?);
+    let mut writer = BufWriter::new(File::create(destination)?);
+    decrypt_stream(key, reader, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Decrypt a stream produced by [`encrypt_stream()`]
+pub fn decrypt_stream(
+    key: &Key<Aes256Gcm>,
+    mut reader: impl Read,
+    mut writer: impl Write,
+) -> anyhow::Result<()> {
+    let cipher = Aes256Gcm::new(key);
+    let base_nonce = read_base_nonce(&mut reader)?;
+
+    let mut chunk = read_chunk(&mut reader, FILE_CHUNK_SIZE + TAG_SIZE)?;
+    for index in 0.. {
+        let next_chunk = read_chunk(&mut reader, FILE_CHUNK_SIZE + TAG_SIZE)?;
+        let is_last = next_chunk.is_empty();
+        let nonce = chunk_nonce(&base_nonce, index, is_last);
+        let plaintext = cipher
+            .decrypt(&nonce, chunk.as_slice())
+            .with_context(|| format!("failed to decrypt chunk {}", index))?;
+        writer.write_all(&plaintext)?;
+
+        if is_last {
+            break;
+        }
+        chunk = next_chunk;
+    }
+
+    Ok(())
+}
+
+/// The size of the encrypted version of a file with the given size
+pub fn encrypted_file_size(plaintext_size: u64) -> u64 {
+    let chunks = plaintext_size.div_ceil(FILE_CHUNK_SIZE as u64).max(1);
+    NONCE_SIZE as u64 + plaintext_size + chunks * TAG_SIZE as u64
+}
+
+/// Check whether the first chunk of an encrypted file can be decrypted with the given key. This is
+/// much cheaper than decrypting the whole file and detects a change of key.
+pub fn is_encrypted_with(key: &Key<Aes256Gcm>, path: &Path) -> anyhow::Result<bool> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let cipher = Aes256Gcm::new(key);
+    let base_nonce = read_base_nonce(&mut reader)?;
+    let chunk = read_chunk(&mut reader, FILE_CHUNK_SIZE + TAG_SIZE)?;
+    let is_last = read_chunk(&mut reader, 1)?.is_empty();
+    let nonce = chunk_nonce(&base_nonce, 0, is_last);
+
+    Ok(cipher.decrypt(&nonce, chunk.as_slice()).is_ok())
+}
+
+fn read_base_nonce(reader: &mut impl Read) -> anyhow::Result<Nonce<Aes256Gcm>> {
+    let mut base_nonce = Nonce::<Aes256Gcm>::default();
+    reader
+        .read_exact(&mut base_nonce)
+        .context("missing nonce")?;
+    Ok(base_nonce)
+}
+
+fn read_chunk(reader: &mut impl Read, max_size: usize) -> anyhow::Result<Vec<u8>> {
+    let mut chunk = Vec::with_capacity(max_size);
+    reader.take(max_size as u64).read_to_end(&mut chunk)?;
+    Ok(chunk)
+}
+
+fn chunk_nonce(base_nonce: &Nonce<Aes256Gcm>, index: u32, is_last: bool) -> Nonce<Aes256Gcm> {
+    let mut nonce = *base_nonce;
+    for (byte, index_byte) in nonce[8..].iter_mut().zip(index.to_be_bytes()) {
+        *byte ^= index_byte;
+    }
+    if is_last {
+        nonce[7] ^= 1;
+    }
+    nonce
+}
+
+pub fn random_key() -> Key<Aes256Gcm> {
+    Aes256Gcm::generate_key(&mut OsRng)
+}
+
+/// A random hex-encoded salt for [`derive_key()`]
+pub fn random_salt() -> String {
+    let mut salt = [0; 16];
+    OsRng.fill_bytes(&mut salt);
+    base16ct::lower::encode_string(&salt)
+}
+
+pub fn derive_key(password: &str, salt: &str, iterations: u32) -> Key<Aes256Gcm> {
     let mut key = Key::<Aes256Gcm>::default();
     pbkdf2_hmac::<Sha256>(password.as_bytes(), salt.as_bytes(), iterations, &mut key);
     key
 }
 
+/// Derive the key of an encrypted video file from the keys of all the rules that grant access to
+/// it. The result is stable across builds, so that the file is only encrypted again when its rules
+/// change, and cannot be computed without the rule keys.
+pub fn derive_file_key(video: &str, rule_keys: &[&Key<Aes256Gcm>]) -> Key<Aes256Gcm> {
+    hash_with_rule_keys(b"lindy-hop file key", video, rule_keys)
+}
+
+/// Derive a file name that can only be guessed with the keys of the rules of the video, like
+/// [`derive_file_key()`]
+pub fn derive_secret_name(video: &str, rule_keys: &[&Key<Aes256Gcm>]) -> String {
+    let hash = hash_with_rule_keys(b"lindy-hop secret name", video, rule_keys);
+    base16ct::lower::encode_string(&hash[0..16])
+}
+
+fn hash_with_rule_keys(
+    purpose: &[u8],
+    video: &str,
+    rule_keys: &[&Key<Aes256Gcm>],
+) -> Key<Aes256Gcm> {
+    let mut hasher = Sha256::new();
+    hasher.update(purpose);
+    hasher.update(b"\0");
+    hasher.update(video.as_bytes());
+    for rule_key in rule_keys {
+        hasher.update(rule_key);
+    }
+    hasher.finalize()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test() {
-        let encrypted = encrypt("1234", "salt", 100_000, "something").unwrap();
-        let decrypted = decrypt("1234", "salt", 100_000, &encrypted).unwrap();
+        let key = derive_key("1234", "salt", 100_000);
+        let encrypted = encrypt(&key, "something").unwrap();
+        let decrypted = decrypt(&key, &encrypted).unwrap();
 
         assert_eq!(decrypted, "something");
     }
+
+    #[test]
+    fn test_deterministic() {
+        let key = derive_key("1234", "salt", 1);
+        let first = encrypt(&key, "something").unwrap();
+        let second = encrypt(&key, "something").unwrap();
+        let other = encrypt(&key, "something else").unwrap();
+
+        assert_eq!(first.iv, second.iv);
+        assert_eq!(first.ciphertext, second.ciphertext);
+        assert_ne!(first.iv, other.iv);
+    }
+
+    #[test]
+    fn test_stream() {
+        let key = derive_key("1234", "salt", 1);
+
+        for size in [0, 1, FILE_CHUNK_SIZE, 2 * FILE_CHUNK_SIZE + 17] {
+            let plaintext: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+            let mut encrypted = Vec::new();
+            encrypt_stream(&key, plaintext.as_slice(), &mut encrypted).unwrap();
+            assert_eq!(encrypted.len() as u64, encrypted_file_size(size as u64));
+
+            let mut decrypted = Vec::new();
+            decrypt_stream(&key, encrypted.as_slice(), &mut decrypted).unwrap();
+            assert_eq!(decrypted, plaintext);
+        }
+    }
+
+    #[test]
+    fn test_stream_tampering() {
+        let key = derive_key("1234", "salt", 1);
+        let plaintext = vec![7; 2 * FILE_CHUNK_SIZE + 17];
+        let mut encrypted = Vec::new();
+        encrypt_stream(&key, plaintext.as_slice(), &mut encrypted).unwrap();
+
+        let truncated = &encrypted[0..NONCE_SIZE + 2 * (FILE_CHUNK_SIZE + TAG_SIZE)];
+        assert!(decrypt_stream(&key, truncated, &mut Vec::new()).is_err());
+
+        let other_key = derive_key("4321", "salt", 1);
+        assert!(decrypt_stream(&other_key, encrypted.as_slice(), &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_file_key() {
+        let key_a = derive_key("1234", "salt", 1);
+        let key_b = derive_key("5678", "salt", 1);
+
+        let file_key = derive_file_key("video.mp4", &[&key_a, &key_b]);
+        assert_eq!(file_key, derive_file_key("video.mp4", &[&key_a, &key_b]));
+        assert_ne!(file_key, derive_file_key("video.mp4", &[&key_a]));
+        assert_ne!(file_key, derive_file_key("other.mp4", &[&key_a, &key_b]));
+    }
+
+    #[test]
+    fn test_file() {
+        let key = derive_key("1234", "salt", 1);
+        let dir = std::env::temp_dir().join(format!("lindy-hop-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let plaintext_path = dir.join("video.mp4");
+        let encrypted_path = dir.join("video.mp4.enc");
+        let decrypted_path = dir.join("video.decrypted.mp4");
+        std::fs::write(&plaintext_path, vec![3; FILE_CHUNK_SIZE + 1]).unwrap();
+
+        encrypt_file(&key, &plaintext_path, &encrypted_path).unwrap();
+        assert!(is_encrypted_with(&key, &encrypted_path).unwrap());
+        assert!(!is_encrypted_with(&derive_key("4321", "salt", 1), &encrypted_path).unwrap());
+
+        decrypt_file(&key, &encrypted_path, &decrypted_path).unwrap();
+        assert_eq!(
+            std::fs::read(&decrypted_path).unwrap(),
+            std::fs::read(&plaintext_path).unwrap()
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
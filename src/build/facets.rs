@@ -0,0 +1,193 @@
+use crate::build::library::LibraryVideo;
+use crate::build::tag_expression::TagPattern;
+use crate::utils::maybe_read_string;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+const FACETS_PATH: &str = "data/facets.json";
+
+/// The message keys of the names of the built-in facets
+const YEAR_FACET: &str = "facet.year";
+const OTHER_TAGS_FACET: &str = "facet.tags";
+
+/// The facets of the filter page, declared in `data/facets.json`, like
+/// `{"facets": [{"name": "Profs", "tags": ["Alice", "Bob"]}, {"name": "Événements", "tags": ["Camp *"]}]}`
+#[derive(Debug, Default, Deserialize)]
+pub struct FacetDefinitions {
+    pub facets: Vec<FacetDefinition>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FacetDefinition {
+    pub name: String,
+    /// A tag belongs to the first facet with a matching pattern
+    pub tags: Vec<TagPattern>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Facet {
+    /// The name of a declared facet, or the message key of a built-in one
+    pub name: String,
+    pub builtin: bool,
+    pub values: Vec<FacetValue>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FacetValue {
+    pub name: String,
+    /// The indexes of the videos with this value
+    pub videos: Vec<usize>,
+}
+
+impl FacetDefinitions {
+    /// The index of the declared facet of a tag
+    pub fn facet_of(&self, tag: &str) -> Option<usize> {
+        self.facets
+            .iter()
+            .position(|facet| facet.tags.iter().any(|pattern| pattern.matches(tag)))
+    }
+}
+
+pub fn read_facet_definitions() -> anyhow::Result<FacetDefinitions> {
+    match maybe_read_string(FACETS_PATH)? {
+        None => Ok(FacetDefinitions::default()),
+        Some(data) => serde_json::from_str(&data).context("failed to parse facets"),
+    }
+}
+
+/// Group the values of the videos into facets: the declared ones, the year of the videos, then the
+/// other tags, except the dates. Values go from the most common one, except the years that go from
+/// the most recent one.
+pub fn facets(definitions: &FacetDefinitions, videos: &[&LibraryVideo]) -> Vec<Facet> {
+    let other_tags = definitions.facets.len();
+    let mut tag_values = vec![BTreeMap::<&str, Vec<usize>>::new(); other_tags + 1];
+    let mut year_values = BTreeMap::<_, Vec<usize>>::new();
+
+    for (index, video) in videos.iter().enumerate() {
+        let date = video.date.map(|date| date.to_string());
+        if let Some(date) = video.date {
+            year_values
+                .entry(Reverse(date.year))
+                .or_default()
+                .push(index);
+        }
+
+        for tag in &video.tags {
+            if date.as_ref() == Some(tag) {
+                continue;
+            }
+
+            let facet = definitions.facet_of(tag).unwrap_or(other_tags);
+            tag_values[facet].entry(tag).or_default().push(index);
+        }
+    }
+
+    let facet_values = |values: BTreeMap<&str, Vec<usize>>| {
+        let mut values = values
+            .into_iter()
+            .map(|(name, videos)| FacetValue {
+                name: name.to_string(),
+                videos,
+            })
+            .collect::<Vec<_>>();
+        values.sort_by_key(|value| Reverse(value.videos.len()));
+        values
+    };
+
+    let mut tag_values = tag_values.into_iter();
+    let mut facets = Vec::new();
+    for definition in &definitions.facets {
+        facets.push(Facet {
+            name: definition.name.clone(),
+            builtin: false,
+            values: facet_values(tag_values.next().unwrap_or_default()),
+        });
+    }
+    facets.push(Facet {
+        name: YEAR_FACET.to_string(),
+        builtin: true,
+        values: year_values
+            .into_iter()
+            .map(|(Reverse(year), videos)| FacetValue {
+                name: year.to_string(),
+                videos,
+            })
+            .collect(),
+    });
+    facets.push(Facet {
+        name: OTHER_TAGS_FACET.to_string(),
+        builtin: true,
+        values: facet_values(tag_values.next().unwrap_or_default()),
+    });
+
+    facets.retain(|facet| !facet.values.is_empty());
+    facets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_facets() {
+        let definitions: FacetDefinitions = serde_json::from_str(
+            r#"{"facets": [
+                {"name": "Profs", "tags": ["Alice", "Bob"]},
+                {"name": "Events", "tags": ["Camp *", "Alice"]},
+                {"name": "Empty", "tags": ["Nobody"]}
+            ]}"#,
+        )
+        .unwrap();
+        let videos = [
+            LibraryVideo::for_test(
+                "a",
+                "2024-03-01",
+                &["2024-03-01", "Alice", "Camp Été", "swing out"],
+                false,
+            ),
+            LibraryVideo::for_test(
+                "b",
+                "2023-05-01",
+                &["2023-05-01", "Alice", "Bob", "swing out"],
+                false,
+            ),
+            LibraryVideo::for_test("c", "", &["Alice", "Bob", "Camp Hiver", "lindy"], false),
+        ];
+        let facets = facets(&definitions, &videos.iter().collect::<Vec<_>>());
+
+        let summary = facets
+            .iter()
+            .map(|facet| {
+                let values = facet
+                    .values
+                    .iter()
+                    .map(|value| (value.name.as_str(), value.videos.clone()))
+                    .collect::<Vec<_>>();
+                (facet.name.as_str(), facet.builtin, values)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "Profs",
+                    false,
+                    vec![("Alice", vec![0, 1, 2]), ("Bob", vec![1, 2])]
+                ),
+                (
+                    "Events",
+                    false,
+                    vec![("Camp Hiver", vec![2]), ("Camp Été", vec![0])]
+                ),
+                (YEAR_FACET, true, vec![("2024", vec![0]), ("2023", vec![1])]),
+                (
+                    OTHER_TAGS_FACET,
+                    true,
+                    vec![("swing out", vec![0, 1]), ("lindy", vec![2])]
+                ),
+            ]
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use crate::build::ingest_times::record_ingest_time;
+use crate::build::TAGGING_IN_PROGRESS_DIR;
 use crate::hash_file;
 use crate::tags_file::{TagsFile, TagsVideo};
 use crate::utils::list_dirs;
@@ -8,17 +10,19 @@ use std::path::Path;
 pub fn ingest_tagging_in_progress(all_tags: &mut TagsFile) -> anyhow::Result<()> {
     fs::create_dir_all("data/videos")?;
 
-    for part_dir in list_dirs("data/tagging_in_progress")? {
+    for part_dir in list_dirs(TAGGING_IN_PROGRESS_DIR)? {
         ingest_tags(all_tags, &part_dir)?;
     }
 
     Ok(())
 }
 
-fn ingest_tags(all_tags: &mut TagsFile, part_dir: &Path) -> anyhow::Result<()> {
+/// Ingest the tagged videos of a single part, returning how many were ingested
+pub fn ingest_tags(all_tags: &mut TagsFile, part_dir: &Path) -> anyhow::Result<usize> {
     let tags_path = part_dir.join("tags.txt");
     let mut tags: TagsFile = fs::read_to_string(&tags_path)?.parse()?;
 
+    let mut ingested = 0;
     let mut pending_videos = Vec::new();
     for video in tags.videos {
         if !video.tags.is_empty() {
@@ -26,6 +30,7 @@ fn ingest_tags(all_tags: &mut TagsFile, part_dir: &Path) -> anyhow::Result<()> {
             ingest_video(all_tags, part_dir, video).with_context(|| {
                 format!("failed to ingest {} from {}", name, part_dir.display())
             })?;
+            ingested += 1;
         } else {
             pending_videos.push(video);
         }
@@ -35,12 +40,13 @@ fn ingest_tags(all_tags: &mut TagsFile, part_dir: &Path) -> anyhow::Result<()> {
         log::info!("finished {}", part_dir.display());
         fs::remove_file(tags_path)?;
         fs::remove_dir(part_dir)?;
-    } else {
+    } else if ingested > 0 {
+        // Only rewrite when something changed, so that a watcher is not woken up by its own writes
         tags.videos = pending_videos;
         fs::write(tags_path, tags.to_string())?;
     }
 
-    Ok(())
+    Ok(ingested)
 }
 
 fn ingest_video(all_tags: &mut TagsFile, part_dir: &Path, video: TagsVideo) -> anyhow::Result<()> {
@@ -59,6 +65,7 @@ fn ingest_video(all_tags: &mut TagsFile, part_dir: &Path, video: TagsVideo) -> a
     if !fs::exists(&destination)? {
         log::info!("Move {} to {}", source.display(), destination);
         fs::rename(&source, &destination)?;
+        record_ingest_time(&new_name)?;
 
         all_tags.videos.push(TagsVideo {
             name: new_name,
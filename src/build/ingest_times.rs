@@ -0,0 +1,55 @@
+use crate::build::date::Date;
+use crate::utils::maybe_read_string;
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::SystemTime;
+
+const INGEST_TIMES_PATH: &str = "data/ingest_times.json";
+
+/// Read when each video was ingested, in seconds since the Unix epoch. Videos ingested before these
+/// times were recorded are missing.
+pub fn read_ingest_times() -> anyhow::Result<BTreeMap<String, u64>> {
+    match maybe_read_string(INGEST_TIMES_PATH)? {
+        None => Ok(BTreeMap::new()),
+        Some(data) => serde_json::from_str(&data)
+            .with_context(|| format!("failed to parse {}", INGEST_TIMES_PATH)),
+    }
+}
+
+/// Record that the video was just ingested
+pub fn record_ingest_time(video: &str) -> anyhow::Result<()> {
+    let mut times = read_ingest_times()?;
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_secs();
+    times.insert(video.to_string(), now);
+
+    fs::write(INGEST_TIMES_PATH, serde_json::to_string_pretty(&times)?)?;
+    Ok(())
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 timestamp in UTC, like
+/// `2024-03-02T18:30:00Z`
+pub fn format_timestamp(seconds: u64) -> String {
+    let date = Date::from_days_since_epoch((seconds / 86_400) as i64);
+    let time = seconds % 86_400;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        date,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(1_709_404_245), "2024-03-02T18:30:45Z");
+    }
+}
@@ -1,56 +1,124 @@
-use crate::build::encrypt::encrypt;
-use crate::build::restrictions::{RestrictionRule, Restrictions};
-use crate::config::Config;
-use crate::tags_file::{TagsFile, TagsVideo};
+use crate::build::date::Date;
+use crate::build::encrypt::{encrypt, Encrypted};
+use crate::build::restrictions::{Restrictions, RuleState, Visibility};
+use crate::build::rule_keys::{RuleKey, WrappedKey};
+use crate::build::sync_build_videos::encrypted_name;
+use crate::build::video_metadata::VideoMetadata;
+use crate::tags_file::TagsVideo;
+use aes_gcm::{Aes256Gcm, Key};
 use anyhow::Context;
-use regex::Regex;
 use serde::Serialize;
 use std::cmp::Reverse;
 use std::collections::BTreeMap;
-use std::sync::LazyLock;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Default)]
 pub struct Library {
     pub videos: Vec<LibraryVideo>,
+    /// The collections of restricted videos, one per active rule
+    pub collections: Vec<LibraryCollection>,
 }
 
 #[derive(Debug, Serialize)]
+pub struct LibraryCollection {
+    pub rule: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
 pub struct LibraryVideo {
     pub date: Option<Date>,
     pub tags: Vec<String>,
     pub thumbnail: String,
     pub video: String,
-    pub restriction: Option<LibraryRestriction>,
+    /// One entry per rule that grants access to the video. Empty for public videos
+    pub restrictions: Vec<LibraryRestriction>,
+    /// Whether the tags and thumbnail must only be revealed after the password is entered
+    pub hidden: bool,
+    /// When the video was ingested, in seconds since the Unix epoch
+    pub ingested_at: Option<u64>,
+    /// Unknown for the hidden videos, which are not probed, and the videos that `ffprobe` failed to
+    /// read
+    pub metadata: Option<VideoMetadata>,
 }
 
-#[derive(Debug, Serialize, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
-pub struct Date {
-    pub year: u16,
-    pub month: u8,
-    pub day: u8,
+#[cfg(test)]
+impl LibraryVideo {
+    /// A public 30 second video, with its date parsed from `date` when it is not empty
+    pub fn for_test(name: &str, date: &str, tags: &[&str], hidden: bool) -> Self {
+        LibraryVideo {
+            date: date.parse().ok(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            thumbnail: String::new(),
+            video: name.to_string(),
+            restrictions: Vec::new(),
+            hidden,
+            ingested_at: None,
+            metadata: Some(VideoMetadata {
+                width: 1280,
+                height: 720,
+                duration_seconds: 30.,
+            }),
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, PartialEq)]
 pub struct LibraryRestriction {
     pub rule: String,
-    pub iv: String,
-    pub ciphertext: String,
+    pub key: WrappedKey,
+    /// The [`AccessPayload`], encrypted with the content key of the rule
+    pub payload: Encrypted,
+}
+
+/// The secret information revealed to whoever knows the password of one of the video rules
+#[derive(Debug, Serialize)]
+struct AccessPayload<'a> {
+    video: &'a str,
+    /// The hex-encoded key of the video file, when it is stored encrypted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    /// The thumbnail file name, for hidden videos
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thumbnail: Option<&'a str>,
+    /// The tags, for hidden videos
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<&'a [String]>,
+}
+
+/// What is known about the files of the published videos, by video name
+#[derive(Debug)]
+pub struct VideoFiles<'a> {
+    /// The keys of the videos that are stored encrypted in the build
+    pub file_keys: &'a BTreeMap<String, Key<Aes256Gcm>>,
+    pub thumbnails: &'a BTreeMap<String, String>,
+    pub ingest_times: &'a BTreeMap<String, u64>,
+    pub metadata: &'a BTreeMap<String, VideoMetadata>,
 }
 
 pub fn create_library(
-    config: &Config,
-    tags_file: &TagsFile,
+    videos: &[&TagsVideo],
     restrictions: &Restrictions,
-    thumbnails: &BTreeMap<String, String>,
+    today: Date,
+    rule_keys: &BTreeMap<String, RuleKey>,
+    files: &VideoFiles,
 ) -> anyhow::Result<Library> {
     let mut library = Library {
-        videos: Vec::with_capacity(tags_file.videos.len()),
+        videos: Vec::with_capacity(videos.len()),
+        collections: restrictions
+            .rules
+            .iter()
+            .filter(|rule| rule.state(today) == RuleState::Active)
+            .map(|rule| LibraryCollection {
+                rule: rule.name.clone(),
+                description: rule.description.clone(),
+            })
+            .collect(),
     };
 
-    for video in &tags_file.videos {
+    for video in videos {
         library
             .videos
-            .push(convert_video(config, restrictions, video, thumbnails)?);
+            .push(convert_video(restrictions, today, rule_keys, video, files)?);
     }
 
     library
@@ -61,65 +129,90 @@ pub fn create_library(
 }
 
 fn convert_video(
-    config: &Config,
     restrictions: &Restrictions,
+    today: Date,
+    rule_keys: &BTreeMap<String, RuleKey>,
     video: &TagsVideo,
-    thumbnails: &BTreeMap<String, String>,
+    files: &VideoFiles,
 ) -> anyhow::Result<LibraryVideo> {
-    let restriction = match restrictions.find(video) {
-        None => None,
-        Some(rule) => Some(create_file_access(config, &video.name, rule)?),
-    };
+    let rules = restrictions.find_all(video, today);
+    let hidden = rules
+        .iter()
+        .any(|rule| rule.visibility == Visibility::Hidden);
 
     let mut tags = video.tags.clone();
     tags.sort();
+    let thumbnail = files
+        .thumbnails
+        .get(&video.name)
+        .context("missing thumbnail")?
+        .clone();
+
+    let encrypted_name = encrypted_name(&video.name);
+    let file_key = files.file_keys.get(&video.name);
+    let payload = serde_json::to_string(&AccessPayload {
+        // The front-end knows from the `.enc` extension that it has to decrypt the file itself
+        video: match file_key {
+            None => &video.name,
+            Some(_) => &encrypted_name,
+        },
+        key: file_key.map(|key| base16ct::lower::encode_string(key)),
+        thumbnail: hidden.then_some(thumbnail.as_str()),
+        tags: hidden.then_some(tags.as_slice()),
+    })?;
+
+    let mut access = Vec::new();
+    for rule in rules {
+        let rule_key = rule_keys.get(&rule.name).context("missing rule key")?;
+        access.push(LibraryRestriction {
+            rule: rule.name.clone(),
+            key: rule_key.wrapped.clone(),
+            payload: encrypt(&rule_key.content_key, &payload)?,
+        });
+    }
 
     Ok(LibraryVideo {
         date: extract_date(&video.tags),
         tags,
         video: video.name.clone(),
-        thumbnail: thumbnails
-            .get(&video.name)
-            .context("missing thumbnail")?
-            .clone(),
-        restriction,
+        thumbnail,
+        restrictions: access,
+        hidden,
+        ingested_at: files.ingest_times.get(&video.name).copied(),
+        metadata: files.metadata.get(&video.name).copied(),
     })
 }
 
+/// The date of the first tag that is a valid date. Tags that only look like one, like
+/// `2024-13-01`, are left as plain tags.
 fn extract_date(tags: &[String]) -> Option<Date> {
-    static DATE_REGEX: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"^(\d\d\d\d)-(\d\d)-(\d\d)$").unwrap());
-
-    for tag in tags {
-        if let Some(captures) = DATE_REGEX.captures(tag) {
-            let year = captures[1].parse();
-            let month = captures[2].parse();
-            let day = captures[3].parse();
-
-            if let (Ok(year), Ok(month), Ok(day)) = (year, month, day) {
-                return Some(Date { year, month, day });
-            }
-        }
-    }
-
-    None
+    tags.iter().find_map(|tag| tag.parse().ok())
 }
 
-fn create_file_access(
-    config: &Config,
-    video_name: &str,
-    rule: &RestrictionRule,
-) -> anyhow::Result<LibraryRestriction> {
-    let encrypted = encrypt(
-        &rule.password,
-        &config.file_access_salt,
-        config.file_access_iterations,
-        video_name,
-    )?;
-
-    Ok(LibraryRestriction {
-        rule: rule.name.clone(),
-        iv: encrypted.iv,
-        ciphertext: encrypted.ciphertext,
-    })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_date() {
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            extract_date(&tags(&["Alice", "2024-03-09"])),
+            "2024-03-09".parse().ok()
+        );
+        assert_eq!(
+            extract_date(&tags(&[
+                "2024-13-01",
+                "2024-00-05",
+                "2024-02-30",
+                "2024-04-31"
+            ])),
+            None
+        );
+        assert_eq!(
+            extract_date(&tags(&["2024-13-01", "2023-12-31"])),
+            "2023-12-31".parse().ok()
+        );
+    }
 }
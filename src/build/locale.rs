@@ -0,0 +1,160 @@
+use anyhow::Context;
+use std::collections::BTreeMap;
+
+const FR_MONTH_NAMES: [&str; 12] = [
+    "janvier",
+    "février",
+    "mars",
+    "avril",
+    "mai",
+    "juin",
+    "juillet",
+    "août",
+    "septembre",
+    "octobre",
+    "novembre",
+    "décembre",
+];
+
+const EN_MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// A language of the site. The pages of each locale are rendered into their own subtree of the
+/// build, the default one at the root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    Fr,
+    En,
+}
+
+impl Locale {
+    /// All the locales, starting with the default one
+    pub const ALL: [Locale; 2] = [Locale::Fr, Locale::En];
+
+    /// The language code, used by the `lang` and `hreflang` attributes
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::Fr => "fr",
+            Locale::En => "en",
+        }
+    }
+
+    /// The name of the language, in that language
+    pub fn name(self) -> &'static str {
+        match self {
+            Locale::Fr => "Français",
+            Locale::En => "English",
+        }
+    }
+
+    /// The directory of the pages of the locale, relative to the root of the site
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Locale::Fr => "",
+            Locale::En => "en/",
+        }
+    }
+
+    /// The name of a month, from 1 for January
+    pub fn month_name(self, month: u8) -> &'static str {
+        let names = match self {
+            Locale::Fr => &FR_MONTH_NAMES,
+            Locale::En => &EN_MONTH_NAMES,
+        };
+        names[usize::from(month) - 1]
+    }
+}
+
+/// The message catalogue of a locale, from `web_src/locales/<code>.json`. Messages take positional
+/// arguments, written `{0}`, `{1}`...
+#[derive(Debug, Clone)]
+pub struct Messages {
+    pub locale: Locale,
+    messages: BTreeMap<String, String>,
+}
+
+impl Messages {
+    pub fn parse(locale: Locale, data: &str) -> anyhow::Result<Self> {
+        let messages = serde_json::from_str(data)
+            .with_context(|| format!("failed to parse the messages of {}", locale.code()))?;
+        Ok(Messages { locale, messages })
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.messages.keys().map(String::as_str)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.messages.contains_key(key)
+    }
+
+    /// The message, or its key if it is missing
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.messages.get(key).map_or(key, String::as_str)
+    }
+
+    /// The message with its arguments replaced
+    pub fn format(&self, key: &str, args: &[impl AsRef<str>]) -> String {
+        let mut result = String::new();
+        let mut rest = self.get(key);
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let arg = rest.find('}').and_then(|end| {
+                let index: usize = rest[1..end].parse().ok()?;
+                Some((args.get(index)?, end))
+            });
+            match arg {
+                Some((arg, end)) => {
+                    result.push_str(arg.as_ref());
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    result.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// The messages of the front-end, whose keys start with `js.`, without this prefix
+    pub fn js_messages(&self) -> BTreeMap<&str, &str> {
+        self.messages
+            .iter()
+            .filter_map(|(key, message)| Some((key.strip_prefix("js.")?, message.as_str())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let messages = Messages::parse(
+            Locale::En,
+            r#"{"page": "Page {0} of {1}", "odd": "{x} {2} {1"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(messages.format("page", &["2", "{0}"]), "Page 2 of {0}");
+        assert_eq!(messages.format("odd", &["a", "b"]), "{x} {2} {1");
+        assert_eq!(messages.get("missing"), "missing");
+    }
+}
@@ -0,0 +1,159 @@
+use crate::build::restrictions::Restrictions;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use anyhow::bail;
+use itertools::Itertools;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+/// An estimate of how many PBKDF2 iterations per second an attacker can compute, on a few GPUs
+const ATTACKER_ITERATIONS_PER_SECOND: f64 = 1e10;
+
+/// The separator between the words of the generated codes
+const WORD_SEPARATOR: char = '-';
+
+static WORDS: LazyLock<Vec<&str>> =
+    LazyLock::new(|| include_str!("french_words.txt").lines().collect());
+
+/// How strong the codes of the rules must be, declared in `data/restrictions.json`
+#[derive(Debug, Deserialize)]
+pub struct PasswordPolicy {
+    /// The minimum cost of an offline brute-force attack, in bits: the entropy of the code plus the
+    /// `log2` of the number of PBKDF2 iterations
+    #[serde(default = "default_min_strength_bits")]
+    pub min_strength_bits: f64,
+    #[serde(default)]
+    pub on_weak: WeakPasswordAction,
+}
+
+#[derive(Debug, Deserialize, Default, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WeakPasswordAction {
+    #[default]
+    Warn,
+    Fail,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            min_strength_bits: default_min_strength_bits(),
+            on_weak: WeakPasswordAction::default(),
+        }
+    }
+}
+
+fn default_min_strength_bits() -> f64 {
+    50.0
+}
+
+/// Check the codes of all the rules against the policy, warning about or refusing the weak ones
+pub fn check_password_strength(
+    restrictions: &Restrictions,
+    passwords: &BTreeMap<String, String>,
+    iterations: u32,
+) -> anyhow::Result<()> {
+    let policy = &restrictions.password_policy;
+    let mut weak_rules = Vec::new();
+
+    for (rule, password) in passwords {
+        let strength = password_entropy_bits(password) + f64::from(iterations).log2();
+        if strength < policy.min_strength_bits {
+            log::warn!(
+                "Rule {} has a weak code: {:.0} bits of work, below the {:.0} of the policy. It can be \
+                brute-forced offline in {}. Use `restrictions generate-code` for a stronger one",
+                rule,
+                strength,
+                policy.min_strength_bits,
+                describe_duration(strength.exp2() / ATTACKER_ITERATIONS_PER_SECOND)
+            );
+            weak_rules.push(rule);
+        }
+    }
+
+    if policy.on_weak == WeakPasswordAction::Fail && !weak_rules.is_empty() {
+        bail!(
+            "the rules {} have codes weaker than the policy",
+            weak_rules.iter().format(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Estimate the entropy of a code: generated passphrases count for their words, anything else for
+/// the size of its character classes
+pub fn password_entropy_bits(password: &str) -> f64 {
+    let words = password.split(WORD_SEPARATOR).collect_vec();
+    if words.len() > 1 && words.iter().all(|word| WORDS.contains(word)) {
+        return words.len() as f64 * (WORDS.len() as f64).log2();
+    }
+
+    let mut alphabet_size = 0;
+    if password.chars().any(|c| c.is_ascii_digit()) {
+        alphabet_size += 10;
+    }
+    if password.chars().any(|c| c.is_ascii_lowercase()) {
+        alphabet_size += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_uppercase()) {
+        alphabet_size += 26;
+    }
+    if password.chars().any(|c| !c.is_ascii_alphanumeric()) {
+        alphabet_size += 33;
+    }
+
+    password.chars().count() as f64 * f64::from(alphabet_size).max(1.0).log2()
+}
+
+/// Generate a memorable code of random French words, like `tigre-velo-lune-pont`
+pub fn generate_code(words: usize) -> String {
+    (0..words)
+        .map(|_| WORDS[random_index(WORDS.len())])
+        .join(&WORD_SEPARATOR.to_string())
+}
+
+/// A uniformly random index below `len`, without modulo bias
+fn random_index(len: usize) -> usize {
+    let len = len as u32;
+    let limit = u32::MAX - u32::MAX % len;
+    loop {
+        let value = OsRng.next_u32();
+        if value < limit {
+            return (value % len) as usize;
+        }
+    }
+}
+
+fn describe_duration(seconds: f64) -> String {
+    const UNITS: [(f64, &str); 5] = [
+        (365.0 * 86_400.0, "years"),
+        (86_400.0, "days"),
+        (3_600.0, "hours"),
+        (60.0, "minutes"),
+        (1.0, "seconds"),
+    ];
+
+    for (unit_seconds, name) in UNITS {
+        if seconds >= unit_seconds {
+            return format!("about {:.0} {}", seconds / unit_seconds, name);
+        }
+    }
+    "less than a second".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entropy() {
+        assert!((password_entropy_bits("1234") - 4.0 * 10f64.log2()).abs() < 1e-9);
+        assert!((password_entropy_bits("abC1") - 4.0 * 62f64.log2()).abs() < 1e-9);
+
+        let code = generate_code(4);
+        assert_eq!(code.split('-').count(), 4);
+        assert!((password_entropy_bits(&code) - 4.0 * (WORDS.len() as f64).log2()).abs() < 1e-9);
+    }
+}
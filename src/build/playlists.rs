@@ -0,0 +1,177 @@
+use crate::build::library::LibraryVideo;
+use crate::utils::list_files;
+use anyhow::{bail, ensure, Context};
+use std::fs;
+
+pub const PLAYLISTS_DIR: &str = "data/playlists";
+
+/// Videos in a chosen order, read from `data/playlists/<name>.txt`
+#[derive(Debug, PartialEq)]
+pub struct Playlist {
+    /// The file name, without its extension
+    pub name: String,
+    pub title: String,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PlaylistEntry {
+    /// The file name of the video
+    pub video: String,
+    pub note: Option<String>,
+}
+
+/// Read all the playlists, sorted by name, checking that each entry matches a single published
+/// video that is not hidden
+pub fn read_playlists(
+    videos: &[LibraryVideo],
+    min_reference_len: usize,
+) -> anyhow::Result<Vec<Playlist>> {
+    if !fs::exists(PLAYLISTS_DIR)? {
+        return Ok(Vec::new());
+    }
+
+    let mut files = list_files(PLAYLISTS_DIR)?;
+    files.retain(|file| file.extension().is_some_and(|extension| extension == "txt"));
+    files.sort();
+
+    let mut playlists = Vec::with_capacity(files.len());
+    for file in files {
+        let name = file
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .context("invalid playlist file name")?;
+        let content = fs::read_to_string(&file)?;
+        let playlist = parse_playlist(name, &content, videos, min_reference_len)
+            .with_context(|| format!("invalid playlist {}", file.display()))?;
+        playlists.push(playlist);
+    }
+
+    Ok(playlists)
+}
+
+/// Parse a playlist: an optional title line starting with `#`, then one video per line, referenced
+/// by its file name, its hash or the start of its hash, optionally followed by a note. Empty lines
+/// are ignored.
+fn parse_playlist(
+    name: &str,
+    content: &str,
+    videos: &[LibraryVideo],
+    min_reference_len: usize,
+) -> anyhow::Result<Playlist> {
+    let mut title = None;
+    let mut entries = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(line_title) = line.strip_prefix('#') {
+            ensure!(
+                title.is_none() && entries.is_empty(),
+                "line {}: the title must be the first line",
+                index + 1
+            );
+            title = Some(line_title.trim().to_string());
+            continue;
+        }
+
+        let (reference, note) = match line.split_once(char::is_whitespace) {
+            None => (line, None),
+            Some((reference, note)) => (reference, Some(note.trim().to_string())),
+        };
+        let video = find_video(reference, videos, min_reference_len)
+            .with_context(|| format!("line {}", index + 1))?;
+        entries.push(PlaylistEntry {
+            video: video.to_string(),
+            note,
+        });
+    }
+
+    ensure!(!entries.is_empty(), "the playlist has no video");
+
+    Ok(Playlist {
+        name: name.to_string(),
+        title: title.unwrap_or_else(|| name.to_string()),
+        entries,
+    })
+}
+
+/// Find the video of a playlist entry. Hidden videos are rejected, since the playlist page and its
+/// notes would reveal them.
+fn find_video<'a>(
+    reference: &str,
+    videos: &'a [LibraryVideo],
+    min_reference_len: usize,
+) -> anyhow::Result<&'a str> {
+    ensure!(
+        reference.len() >= min_reference_len,
+        "the video reference {:?} is shorter than {} characters",
+        reference,
+        min_reference_len
+    );
+
+    let mut matches = videos
+        .iter()
+        .filter(|video| video.video.starts_with(reference));
+    match (matches.next(), matches.next()) {
+        (Some(video), None) if video.hidden => {
+            bail!(
+                "the video {:?} is hidden, it cannot be in a playlist",
+                reference
+            )
+        }
+        (Some(video), None) => Ok(&video.video),
+        (None, _) => bail!("no published video matches {:?}", reference),
+        (Some(_), Some(_)) => bail!("several videos match {:?}", reference),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_playlist() {
+        let videos = [
+            LibraryVideo::for_test("aaaa1111.mp4", "", &[], false),
+            LibraryVideo::for_test("aaaa2222.mp4", "", &[], false),
+            LibraryVideo::for_test("bbbb3333.mp4", "", &[], false),
+            LibraryVideo::for_test("dddd4444.mp4", "", &[], true),
+        ];
+        let playlist = parse_playlist(
+            "swing-out",
+            "# Swing-out progression\n\naaaa2 Slowly first\nbbbb3333.mp4\n",
+            &videos,
+            4,
+        )
+        .unwrap();
+
+        assert_eq!(
+            playlist,
+            Playlist {
+                name: "swing-out".to_string(),
+                title: "Swing-out progression".to_string(),
+                entries: vec![
+                    PlaylistEntry {
+                        video: "aaaa2222.mp4".to_string(),
+                        note: Some("Slowly first".to_string()),
+                    },
+                    PlaylistEntry {
+                        video: "bbbb3333.mp4".to_string(),
+                        note: None,
+                    },
+                ],
+            }
+        );
+
+        assert!(parse_playlist("x", "aaaa", &videos, 4).is_err());
+        assert!(parse_playlist("x", "cccc", &videos, 4).is_err());
+        assert!(parse_playlist("x", "aa", &videos, 2).is_err());
+        assert!(parse_playlist("x", "bbbb\n# Title", &videos, 4).is_err());
+        assert!(parse_playlist("x", "# Title", &videos, 4).is_err());
+        assert!(parse_playlist("x", "dddd", &videos, 4).is_err());
+    }
+}
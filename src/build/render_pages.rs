@@ -1,52 +1,336 @@
-use crate::build::library::Library;
+use crate::build::archive::{archive, ArchiveMonth, ArchiveYear};
+use crate::build::date::Date;
+use crate::build::facets::{read_facet_definitions, Facet, FacetDefinitions};
+use crate::build::ingest_times::format_timestamp;
+use crate::build::library::{Library, LibraryVideo};
+use crate::build::locale::{Locale, Messages};
+use crate::build::playlists::{read_playlists, Playlist};
+use crate::build::rule_keys::ACCESS_FORMAT_VERSION;
+use crate::build::search_index::search_index;
+use crate::build::staged_output::StagedOutput;
+use crate::build::tag_stats::{format_duration, tag_stats, TagStats};
+use crate::build::video_neighbors::{video_neighbors, Neighbors, VideoNeighbors};
+use crate::build::web_assets::{asset_binary_data, asset_data, static_asset_names};
 use crate::config::Config;
-use anyhow::Context;
-use handlebars::Handlebars;
+use anyhow::{ensure, Context};
+use handlebars::{
+    Handlebars, Helper, HelperDef, JsonRender, JsonValue, RenderContext, RenderError,
+    RenderErrorReason, ScopedJson,
+};
 use itertools::Itertools;
-use rust_embed::Embed;
 use serde::Serialize;
-use std::fs;
-use std::path::Path;
-use std::time::SystemTime;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
 use unidecode::unidecode;
 
-pub fn render_pages(config: &Config, library: &Library) -> anyhow::Result<()> {
-    let handlebars = handlebars()?;
-
-    let home_data = home_page_data(config, library)?;
-    let rendered = handlebars.render("home_page", &home_data)?;
-    fs::write("build/index.html", rendered)?;
-
-    let _ = fs::remove_dir_all("build/video");
-    fs::create_dir_all("build/video")?;
-    for video in &home_data.videos {
-        let video_data = VideoPageData {
-            public_url: &config.public_url,
-            page_title: format!(
-                "Vidéo Lindy Hop - {}",
-                video.tags.iter().map(|tag| &tag.name).format(", ")
-            ),
-            build_time: home_data.build_time,
-            access_salt: home_data.access_salt,
-            access_iterations: home_data.access_iterations,
-            video,
-            thumbnail_height: home_data.thumbnail_height,
-        };
-        let rendered = handlebars.render("video_page", &video_data)?;
+/// The number of entries of each feed
+const FEED_SIZE: usize = 50;
+
+/// The number of related tags shown on a tag page, besides those of the declared facets
+const RELATED_TAGS: usize = 10;
+
+/// The number of font sizes of the tag cloud
+const TAG_CLOUD_WEIGHTS: usize = 5;
+
+/// Which video and tag pages should be rendered. The other pages and the static files are always
+/// rendered.
+#[derive(Debug)]
+pub enum PageSelection {
+    All,
+    /// Only the pages of these videos (by file name) and tags. Selected videos and tags that are no
+    /// longer part of the library have their pages removed.
+    Only {
+        videos: BTreeSet<String>,
+        tags: BTreeSet<String>,
+    },
+}
+
+impl PageSelection {
+    fn contains_video(&self, video: &str) -> bool {
+        match self {
+            PageSelection::All => true,
+            PageSelection::Only { videos, .. } => videos.contains(video),
+        }
+    }
+
+    fn contains_tag(&self, tag: &str) -> bool {
+        match self {
+            PageSelection::All => true,
+            PageSelection::Only { tags, .. } => tags.contains(tag),
+        }
+    }
+}
+
+pub fn render_pages(
+    config: &Config,
+    library: &Library,
+    selection: &PageSelection,
+) -> anyhow::Result<()> {
+    let mut output = StagedOutput::new("build");
+    // The public pages of all the locales, listed in the sitemap
+    let mut sitemap = Vec::new();
+
+    let playlists = read_playlists(&library.videos, config.thumbnail_hex_chars_prefix)?;
+    let home_data = home_page_data(config, library, &playlists)?;
+    let facet_definitions = read_facet_definitions()?;
+    let index = search_index(config, library, &facet_definitions);
+    output.write("build/search_index.json", serde_json::to_string(&index)?);
+
+    // Hidden videos are left out, since their tags must stay secret
+    let visible_videos = library
+        .videos
+        .iter()
+        .filter(|video| !video.hidden)
+        .collect_vec();
+    let stats = tag_stats(&visible_videos);
+    let tags_data = TagsData {
+        facet_definitions: &facet_definitions,
+        facets: &index.facets,
+        cloud: tag_cloud(&stats),
+        stats,
+    };
+
+    let renderers = locale_messages()?
+        .into_iter()
+        .map(|messages| LocaleRenderer::new(config, messages))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    for renderer in &renderers {
+        renderer.render_pages(
+            library,
+            selection,
+            &home_data,
+            &tags_data,
+            &mut output,
+            &mut sitemap,
+        )?;
+        renderer.render_playlists(&playlists, library, &home_data, &mut output, &mut sitemap)?;
+    }
+
+    let sitemap_data = SitemapData {
+        public_url: &config.public_url,
+        pages: &sitemap,
+    };
+    output.write(
+        "build/sitemap.xml",
+        renderers[0].handlebars.render("sitemap", &sitemap_data)?,
+    );
+    output.write(
+        "build/robots.txt",
+        format!(
+            "User-agent: *\n{}\nSitemap: {}/sitemap.xml\n",
+            Locale::ALL
+                .iter()
+                .map(|locale| format!("Disallow: /{}collection/\n", locale.prefix()))
+                .join(""),
+            config.public_url
+        ),
+    );
+
+    // At the root, so that it can control all the pages
+    output.write(
+        "build/decrypt_worker.js",
+        asset_binary_data("decrypt_worker.js")?,
+    );
+
+    output.prune("build/static");
+    for item in static_asset_names()? {
+        output.write(format!("build/{}", item), asset_binary_data(&item)?);
+    }
+
+    output.commit()
+}
+
+/// Read the message catalogues of all the locales, that must all define the same messages
+fn locale_messages() -> anyhow::Result<Vec<Messages>> {
+    let all = Locale::ALL
+        .iter()
+        .map(|&locale| {
+            Messages::parse(
+                locale,
+                &asset_data(&format!("locales/{}.json", locale.code()))?,
+            )
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let default_keys = all[0].keys().collect::<BTreeSet<_>>();
+    for messages in &all[1..] {
+        let keys = messages.keys().collect::<BTreeSet<_>>();
+        ensure!(
+            keys == default_keys,
+            "the messages of {} differ from the default ones: {}",
+            messages.locale.code(),
+            keys.symmetric_difference(&default_keys).format(", ")
+        );
+    }
+
+    Ok(all)
+}
+
+/// Renders the pages of a locale into its subtree of the build
+struct LocaleRenderer<'a> {
+    config: &'a Config,
+    messages: Messages,
+    handlebars: Handlebars<'static>,
+}
+
+impl<'a> LocaleRenderer<'a> {
+    fn new(config: &'a Config, messages: Messages) -> anyhow::Result<Self> {
+        Ok(LocaleRenderer {
+            config,
+            handlebars: handlebars(&messages)?,
+            messages,
+        })
+    }
+
+    /// The path of a page of the locale, relative to the root of the site
+    fn path(&self, page: &str) -> String {
+        format!("{}{}", self.messages.locale.prefix(), page)
+    }
+
+    /// The absolute URL of a page of the locale
+    fn url(&self, page: &str) -> String {
+        format!("{}/{}", self.config.public_url, self.path(page))
+    }
+
+    /// Render the page at `page`, relative to the root of the locale. The data is completed with the
+    /// relative URLs of the roots of the site and of the locale, and with the same page in each
+    /// locale.
+    fn write(
+        &self,
+        output: &mut StagedOutput,
+        template: &str,
+        page: &str,
+        data: &impl Serialize,
+    ) -> anyhow::Result<()> {
+        let path = self.path(page);
+        let base_url = base_url(&path);
+        let alternates = Locale::ALL
+            .iter()
+            .map(|&locale| Alternate {
+                lang: locale.code(),
+                name: locale.name(),
+                url: format!("{}/{}{}", self.config.public_url, locale.prefix(), page),
+                link: format!("{}/{}{}", base_url, locale.prefix(), page),
+                current: locale == self.messages.locale,
+            })
+            .collect_vec();
+
+        let mut data = serde_json::to_value(data)?;
+        let fields = data
+            .as_object_mut()
+            .context("page data must be an object")?;
+        fields.insert("lang".to_string(), self.messages.locale.code().into());
+        fields.insert("locale_url".to_string(), self::base_url(page).into());
+        fields.insert("base_url".to_string(), base_url.into());
+        fields.insert("alternates".to_string(), serde_json::to_value(alternates)?);
+
+        let rendered = self.handlebars.render(template, &data)?;
+        output.write(format!("build/{}", path), rendered);
+        Ok(())
+    }
 
-        fs::write(format!("build/video/{}.html", video.short_name), rendered)?;
+    /// Remove the files of the directory of the locale that are not rendered again
+    fn prune(&self, output: &mut StagedOutput, dir: &str) {
+        output.prune(format!("build/{}", self.path(dir)));
     }
 
-    let _ = fs::remove_dir_all("build/tag");
-    fs::create_dir_all("build/tag")?;
-    for tag in &home_data.all_tags {
-        let tag_data = TagPageData {
-            selected_tag: &tag.name,
-            page_title: format!("Vidéos Lindy Hop - {}", tag.name),
-            build_time: home_data.build_time,
-            access_salt: home_data.access_salt,
-            access_iterations: home_data.access_iterations,
-            videos: home_data
+    fn render_pages(
+        &self,
+        library: &Library,
+        selection: &PageSelection,
+        home_data: &HomePageData,
+        tags_data: &TagsData,
+        output: &mut StagedOutput,
+        sitemap: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        let config = self.config;
+        let messages = &self.messages;
+
+        let home_pages = paginate(&home_data.videos, config.page_size);
+        self.prune(output, "page");
+        for (index, videos) in home_pages.iter().enumerate() {
+            let path = home_page_path(index + 1);
+            let index_data = IndexPageData {
+                static_version: &home_data.static_version,
+                collections: &home_data.collections,
+                playlists: &home_data.playlists,
+                videos,
+                thumbnail_height: home_data.thumbnail_height,
+                pagination: pagination(index + 1, home_pages.len(), home_page_path),
+                tag_cloud: (index == 0).then_some(&tags_data.cloud),
+            };
+            self.write(output, "home_page", &path, &index_data)?;
+            sitemap.push(self.path(&path));
+        }
+
+        match selection {
+            PageSelection::All => {
+                self.prune(output, "video");
+                self.prune(output, "tag");
+            }
+            PageSelection::Only { videos, tags } => {
+                self.remove_stale_pages(library, home_data, videos, tags, output);
+            }
+        }
+
+        let positions: BTreeMap<_, _> = library
+            .videos
+            .iter()
+            .enumerate()
+            .map(|(position, video)| (video.video.as_str(), position))
+            .collect();
+        let video_link = |video: &LibraryVideo| VideoLink {
+            share_link: &home_data.videos[positions[video.video.as_str()]].share_link,
+            date: video.date.map(|date| date.format(messages.locale)),
+        };
+        let neighbor_links = |neighbors: &Neighbors| NeighborLinks {
+            previous: neighbors.previous.map(video_link),
+            next: neighbors.next.map(video_link),
+        };
+
+        for ((library_video, video), neighbors) in library
+            .videos
+            .iter()
+            .zip(&home_data.videos)
+            .zip(&home_data.neighbors)
+        {
+            if video.video.is_some() {
+                sitemap.push(self.path(&video.share_link));
+            }
+            if !selection.contains_video(&library_video.video) {
+                continue;
+            }
+
+            let video_data = VideoPageData {
+                public_url: &config.public_url,
+                page_title: self.video_page_title(video),
+                static_version: &home_data.static_version,
+                video,
+                thumbnail_height: home_data.thumbnail_height,
+                preview: self.video_preview(library_video, video)?,
+                by_date: neighbor_links(&neighbors.by_date),
+                by_tag: neighbors
+                    .by_tag
+                    .iter()
+                    .map(|(tag, neighbors)| TagNeighbors {
+                        name: tag,
+                        clean_name: clean_name(tag),
+                        neighbors: neighbor_links(neighbors),
+                    })
+                    .collect(),
+                related: neighbors
+                    .related
+                    .iter()
+                    .map(|other| &home_data.videos[positions[other.video.as_str()]])
+                    .collect(),
+            };
+            self.write(output, "video_page", &video.share_link, &video_data)?;
+        }
+
+        for tag in &home_data.all_tags {
+            let tag_videos = home_data
                 .videos
                 .iter()
                 .filter(|video| {
@@ -55,84 +339,607 @@ pub fn render_pages(config: &Config, library: &Library) -> anyhow::Result<()> {
                         .iter()
                         .any(|video_tag| video_tag.name == tag.name)
                 })
-                .collect(),
+                .collect_vec();
+            let tag_pages = paginate(&tag_videos, config.page_size);
+            let tag_page_path = |number| tag_page_path(&tag.clean_name, number);
+            sitemap.extend((1..=tag_pages.len()).map(|number| self.path(&tag_page_path(number))));
+            if !selection.contains_tag(&tag.name) {
+                continue;
+            }
+
+            // The pages after the first one live in their own directory, to remove those beyond the
+            // new page count
+            self.prune(output, &format!("tag/{}", tag.clean_name));
+            let summary = tags_data
+                .stats
+                .get(tag.name.as_str())
+                .map(|stats| self.tag_summary(stats, tags_data.facet_definitions));
+            for (index, videos) in tag_pages.iter().enumerate() {
+                let path = tag_page_path(index + 1);
+                let tag_data = TagPageData {
+                    selected_tag: &tag.name,
+                    page_title: messages.format("title.videos_of", &[&tag.name]),
+                    feed: tag_feed_path(&tag.clean_name),
+                    static_version: &home_data.static_version,
+                    videos,
+                    pagination: pagination(index + 1, tag_pages.len(), tag_page_path),
+                    summary: summary.as_ref(),
+                };
+                self.write(output, "tag_page", &path, &tag_data)?;
+            }
+        }
+
+        self.prune(output, "collection");
+        for collection in &library.collections {
+            let collection_data = CollectionPageData {
+                page_title: messages.format("title.videos_of", &[&collection.rule]),
+                static_version: &home_data.static_version,
+                rule: &collection.rule,
+                description: collection.description.as_deref(),
+                videos: home_data
+                    .videos
+                    .iter()
+                    .filter(|video| {
+                        video
+                            .collections
+                            .iter()
+                            .any(|other| other.name == collection.rule)
+                    })
+                    .collect(),
+                thumbnail_height: home_data.thumbnail_height,
+            };
+            self.write(
+                output,
+                "collection_page",
+                &collection_page(&collection.rule),
+                &collection_data,
+            )?;
+        }
+
+        self.render_archive(library, home_data, output, sitemap)?;
+        self.render_feeds(library, home_data, output)?;
+
+        let filter_data = FilterPageData {
+            static_version: &home_data.static_version,
+            facets: tags_data.facets,
+        };
+        self.write(output, "filter_page", "filtres.html", &filter_data)?;
+        sitemap.push(self.path("filtres.html"));
+
+        self.write(output, "about_page", "a-propos.html", &json!({}))?;
+        sitemap.push(self.path("a-propos.html"));
+
+        Ok(())
+    }
+
+    fn render_playlists(
+        &self,
+        playlists: &[Playlist],
+        library: &Library,
+        home_data: &HomePageData,
+        output: &mut StagedOutput,
+        sitemap: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        self.prune(output, "playlist");
+        for playlist in playlists {
+            let entries = playlist
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| {
+                    let position = library
+                        .videos
+                        .iter()
+                        .position(|video| video.video == entry.video)
+                        .context("missing playlist video")?;
+                    Ok(PlaylistEntryData {
+                        number: index + 1,
+                        note: entry.note.as_deref(),
+                        video: &home_data.videos[position],
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            let page = playlist_page(&playlist.name);
+            let playlist_data = PlaylistPageData {
+                page_title: self.messages.format("title.playlist", &[&playlist.title]),
+                static_version: &home_data.static_version,
+                title: &playlist.title,
+                count: entries.len(),
+                entries,
+                thumbnail_height: home_data.thumbnail_height,
+            };
+            self.write(output, "playlist_page", &page, &playlist_data)?;
+            sitemap.push(self.path(&page));
+        }
+
+        Ok(())
+    }
+
+    /// The statistics of a tag, with its related tags grouped by declared facet
+    fn tag_summary<'t>(
+        &self,
+        stats: &TagStats<'t>,
+        facet_definitions: &'t FacetDefinitions,
+    ) -> TagSummary<'t> {
+        let mut facets = facet_definitions
+            .facets
+            .iter()
+            .map(|definition| RelatedFacet {
+                name: &definition.name,
+                tags: Vec::new(),
+            })
+            .collect_vec();
+        let mut related = Vec::new();
+        for &(name, count) in &stats.related {
+            let link = TagLink {
+                name,
+                clean_name: clean_name(name),
+                count,
+            };
+            match facet_definitions.facet_of(name) {
+                Some(facet) => facets[facet].tags.push(link),
+                None if related.len() < RELATED_TAGS => related.push(link),
+                None => {}
+            }
+        }
+        facets.retain(|facet| !facet.tags.is_empty());
+
+        let format_date = |date: Option<Date>| date.map(|date| date.format(self.messages.locale));
+        TagSummary {
+            count: stats.count,
+            first_date: format_date(stats.first_date),
+            last_date: format_date(stats.last_date),
+            duration: format_duration(stats.duration_seconds),
+            facets,
+            related,
+        }
+    }
+
+    /// Render the archive index, a page per year and month, and a page for the videos without a
+    /// date. Hidden videos are left out, since their date must stay secret.
+    fn render_archive(
+        &self,
+        library: &Library,
+        home_data: &HomePageData,
+        output: &mut StagedOutput,
+        sitemap: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        let messages = &self.messages;
+        let archive = archive(
+            library
+                .videos
+                .iter()
+                .zip(&home_data.videos)
+                .filter(|(library_video, _)| !library_video.hidden)
+                .map(|(library_video, video)| (library_video.date, video)),
+            messages.locale,
+        );
+
+        self.prune(output, "archive");
+
+        let archive_data = ArchivePageData {
+            static_version: &home_data.static_version,
+            years: &archive.years,
+            undated_count: archive.undated.len(),
+        };
+        self.write(output, "archive_page", "archive/index.html", &archive_data)?;
+        sitemap.push(self.path("archive/index.html"));
+
+        for year in &archive.years {
+            let year_data = ArchiveYearPageData {
+                page_title: messages.format("title.videos_of", &[year.year.to_string()]),
+                static_version: &home_data.static_version,
+                year,
+            };
+            self.write(output, "archive_year_page", &year.page, &year_data)?;
+            sitemap.push(self.path(&year.page));
+        }
+
+        let months = archive
+            .years
+            .iter()
+            .flat_map(|year| &year.months)
+            .collect_vec();
+        for (index, month) in months.iter().enumerate() {
+            let month_data = ArchiveMonthPageData {
+                page_title: messages.format("title.videos_of", &[&month.name]),
+                static_version: &home_data.static_version,
+                month,
+                // Months go from the most recent
+                previous: months.get(index + 1).map(|other| ArchiveLink::from(*other)),
+                next: index
+                    .checked_sub(1)
+                    .map(|other| ArchiveLink::from(months[other])),
+                thumbnail_height: home_data.thumbnail_height,
+            };
+            self.write(output, "archive_month_page", &month.page, &month_data)?;
+            sitemap.push(self.path(&month.page));
+        }
+
+        let undated_data = ArchiveUndatedPageData {
+            static_version: &home_data.static_version,
+            videos: &archive.undated,
+            thumbnail_height: home_data.thumbnail_height,
+        };
+        self.write(
+            output,
+            "archive_undated_page",
+            "archive/sans-date.html",
+            &undated_data,
+        )?;
+
+        Ok(())
+    }
+
+    /// Render the feed of the latest ingested videos, and one feed per tag. Restricted videos
+    /// appear without their file name, and hidden videos without their tags, so only in the main
+    /// feed.
+    fn render_feeds(
+        &self,
+        library: &Library,
+        home_data: &HomePageData,
+        output: &mut StagedOutput,
+    ) -> anyhow::Result<()> {
+        let mut ingested = library
+            .videos
+            .iter()
+            .zip(&home_data.videos)
+            .filter_map(|(library_video, video)| Some((library_video.ingested_at?, video)))
+            .collect_vec();
+        ingested.sort_by_key(|(time, video)| (Reverse(*time), video.short_name));
+
+        let home_feed = self.feed_data(
+            self.messages.get("site.title").to_string(),
+            "feed.xml",
+            "index.html",
+            ingested.iter().copied(),
+        );
+        self.write(output, "feed", "feed.xml", &home_feed)?;
+
+        self.prune(output, "feed");
+        for tag in &home_data.all_tags {
+            let path = tag_feed_path(&tag.clean_name);
+            let tag_feed = self.feed_data(
+                self.messages.format("title.videos_of", &[&tag.name]),
+                &path,
+                &tag_page_path(&tag.clean_name, 1),
+                ingested
+                    .iter()
+                    .copied()
+                    .filter(|(_, video)| video.tags.contains(tag)),
+            );
+            self.write(output, "feed", &path, &tag_feed)?;
+        }
+
+        Ok(())
+    }
+
+    fn feed_data<'v>(
+        &self,
+        title: String,
+        path: &str,
+        page: &str,
+        videos: impl Iterator<Item = (u64, &'v VideoData<'v>)>,
+    ) -> FeedData<'v> {
+        let public_url = &self.config.public_url;
+        let entries = videos
+            .take(FEED_SIZE)
+            .map(|(time, video)| FeedEntry {
+                title: self.video_page_title(video),
+                url: self.url(&video.share_link),
+                updated: format_timestamp(time),
+                video_url: video
+                    .video
+                    .map(|name| format!("{}/videos/{}", public_url, name)),
+                thumbnail_url: format!("{}/{}", public_url, video.thumbnail),
+                tags: video.tags.iter().map(|tag| tag.name.as_str()).collect(),
+            })
+            .collect_vec();
+
+        FeedData {
+            title,
+            feed_url: self.url(path),
+            page_url: self.url(page),
+            // Not the build time, so that the feed only changes with its entries
+            updated: entries
+                .first()
+                .map_or_else(|| format_timestamp(0), |entry| entry.updated.clone()),
+            entries,
+        }
+    }
+
+    /// The previews of a public video for social networks and search engines. Restricted videos
+    /// have none, and their pages ask not to be indexed.
+    fn video_preview(
+        &self,
+        library_video: &LibraryVideo,
+        video: &VideoData,
+    ) -> anyhow::Result<Option<VideoPreview>> {
+        let (Some(video_name), Some(metadata)) = (video.video, library_video.metadata) else {
+            return Ok(None);
         };
 
-        let rendered = handlebars.render("tag_page", &tag_data)?;
+        let config = self.config;
+        let public_url = &config.public_url;
+        let title = self.video_page_title(video);
+        let description = self.messages.format(
+            "preview.description",
+            &[video.tags.iter().map(|tag| &tag.name).join(", ")],
+        );
+        let url = self.url(&video.share_link);
+        let image_url = format!("{}/{}", public_url, video.thumbnail);
+        let video_url = format!("{}/videos/{}", public_url, video_name);
+        let duration_seconds = metadata.duration_seconds.round() as u64;
+
+        let mut json_ld = json!({
+            "@context": "https://schema.org",
+            "@type": "VideoObject",
+            "name": title,
+            "description": description,
+            "url": url,
+            "thumbnailUrl": image_url,
+            "contentUrl": video_url,
+            "duration": format!("PT{}S", duration_seconds),
+            "width": metadata.width,
+            "height": metadata.height,
+            "keywords": library_video.tags.join(", "),
+            "inLanguage": self.messages.locale.code(),
+        });
+        let upload_date = library_video
+            .ingested_at
+            .map(format_timestamp)
+            .or_else(|| library_video.date.map(|date| date.to_string()));
+        if let Some(upload_date) = upload_date {
+            json_ld["uploadDate"] = upload_date.into();
+        }
+
+        Ok(Some(VideoPreview {
+            description,
+            url,
+            image_url,
+            image_width: (f64::from(config.thumbnail_height) * f64::from(metadata.width)
+                / f64::from(metadata.height))
+            .round() as u32,
+            image_height: config.thumbnail_height,
+            video_type: video_type(video_name),
+            video_url,
+            width: metadata.width,
+            height: metadata.height,
+            duration_seconds,
+            // Escape `<` so that no tag can close the script element
+            json_ld: serde_json::to_string(&json_ld)?.replace('<', "\\u003c"),
+        }))
+    }
 
-        fs::write(format!("build/tag/{}.html", tag.clean_name), rendered)?;
+    fn video_page_title(&self, video: &VideoData) -> String {
+        if video.hidden {
+            self.messages.get("title.hidden_video").to_string()
+        } else {
+            self.messages.format(
+                "title.video_of",
+                &[video.tags.iter().map(|tag| &tag.name).join(", ")],
+            )
+        }
     }
 
-    let rendered = handlebars.render("about_page", &())?;
-    fs::write("build/a-propos.html", rendered)?;
+    fn remove_stale_pages(
+        &self,
+        library: &Library,
+        home_data: &HomePageData,
+        videos: &BTreeSet<String>,
+        tags: &BTreeSet<String>,
+        output: &mut StagedOutput,
+    ) {
+        for video in videos {
+            if !library.videos.iter().any(|other| &other.video == video) {
+                let short_name = &video[0..self.config.thumbnail_hex_chars_prefix];
+                output.remove(format!(
+                    "build/{}",
+                    self.path(&format!("video/{}.html", short_name))
+                ));
+            }
+        }
 
-    for item in Asset::iter() {
-        if item.starts_with("static/") {
-            let target = format!("build/{}", item);
-            fs::create_dir_all(Path::new(&target).parent().context("missing parent dir")?)?;
-            fs::write(target, asset_binary_data(&item)?)?;
+        for tag in tags {
+            if !home_data.all_tags.iter().any(|other| &other.name == tag) {
+                output.remove(format!(
+                    "build/{}",
+                    self.path(&tag_page_path(&clean_name(tag), 1))
+                ));
+                self.prune(output, &format!("tag/{}", clean_name(tag)));
+            }
         }
     }
+}
+
+/// The tags that are not dates, by name, weighted by their number of videos on a logarithmic scale
+fn tag_cloud<'a>(stats: &BTreeMap<&'a str, TagStats>) -> Vec<CloudTag<'a>> {
+    let max_count = stats.values().map(|stats| stats.count).max().unwrap_or(0);
+    let mut cloud = stats
+        .iter()
+        .filter(|(tag, _)| tag.parse::<Date>().is_err())
+        .map(|(&name, stats)| {
+            let weight = if max_count > 1 {
+                1 + ((TAG_CLOUD_WEIGHTS - 1) as f64 * (stats.count as f64).ln()
+                    / (max_count as f64).ln())
+                .round() as usize
+            } else {
+                1
+            };
+            CloudTag {
+                name,
+                clean_name: clean_name(name),
+                count: stats.count,
+                weight,
+            }
+        })
+        .collect_vec();
+    cloud.sort_by_cached_key(|tag| unidecode(tag.name).to_lowercase());
+    cloud
+}
 
-    Ok(())
+/// The MIME type of a video file, from its extension
+fn video_type(video_name: &str) -> Option<&'static str> {
+    match video_name.rsplit_once('.')?.1.to_lowercase().as_str() {
+        "mp4" | "m4v" => Some("video/mp4"),
+        "webm" => Some("video/webm"),
+        "mov" => Some("video/quicktime"),
+        _ => None,
+    }
 }
 
-fn handlebars() -> anyhow::Result<Handlebars<'static>> {
+/// The `t` helper, that writes a message of the locale, like `{{t "pagination.current" 2 5}}`
+struct MessageHelper(Messages);
+
+impl HelperDef for MessageHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc handlebars::Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let key = h
+            .param(0)
+            .and_then(|param| param.value().as_str())
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex("t", 0))?;
+        if !self.0.contains(key) {
+            return Err(RenderErrorReason::Other(format!("missing message {:?}", key)).into());
+        }
+
+        let args = h.params()[1..]
+            .iter()
+            .map(|param| param.value().render())
+            .collect_vec();
+        Ok(ScopedJson::Derived(JsonValue::String(
+            self.0.format(key, &args),
+        )))
+    }
+}
+
+/// The `js_messages` helper, that writes the messages of the front-end as JSON, to embed in a
+/// script element with `{{{js_messages}}}`
+struct JsMessagesHelper(String);
+
+impl HelperDef for JsMessagesHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        _: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc handlebars::Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        Ok(ScopedJson::Derived(JsonValue::String(self.0.clone())))
+    }
+}
+
+fn handlebars(messages: &Messages) -> anyhow::Result<Handlebars<'static>> {
     let mut handlebars = Handlebars::new();
 
+    handlebars.register_helper("t", Box::new(MessageHelper(messages.clone())));
+    // Escape `<` so that no tag can close the script element
+    let js_messages = serde_json::to_string(&messages.js_messages())?.replace('<', "\\u003c");
+    handlebars.register_helper("js_messages", Box::new(JsMessagesHelper(js_messages)));
+
     handlebars.register_partial("head", asset_data("partials/head.html.hbs")?)?;
+    handlebars.register_partial("languages", asset_data("partials/languages.html.hbs")?)?;
+    handlebars.register_partial("pagination", asset_data("partials/pagination.html.hbs")?)?;
     handlebars.register_partial("video", asset_data("partials/video.html.hbs")?)?;
     handlebars.register_partial("search", asset_data("partials/search.html.hbs")?)?;
 
     handlebars.register_template_string("about_page", asset_data("pages/about_page.html.hbs")?)?;
+    handlebars.register_template_string(
+        "archive_month_page",
+        asset_data("pages/archive_month_page.html.hbs")?,
+    )?;
+    handlebars
+        .register_template_string("archive_page", asset_data("pages/archive_page.html.hbs")?)?;
+    handlebars.register_template_string(
+        "archive_undated_page",
+        asset_data("pages/archive_undated_page.html.hbs")?,
+    )?;
+    handlebars.register_template_string(
+        "archive_year_page",
+        asset_data("pages/archive_year_page.html.hbs")?,
+    )?;
+    handlebars.register_template_string(
+        "collection_page",
+        asset_data("pages/collection_page.html.hbs")?,
+    )?;
+    handlebars.register_template_string("feed", asset_data("pages/feed.xml.hbs")?)?;
+    handlebars
+        .register_template_string("filter_page", asset_data("pages/filter_page.html.hbs")?)?;
     handlebars.register_template_string("home_page", asset_data("pages/home_page.html.hbs")?)?;
+    handlebars
+        .register_template_string("playlist_page", asset_data("pages/playlist_page.html.hbs")?)?;
+    handlebars.register_template_string("sitemap", asset_data("pages/sitemap.xml.hbs")?)?;
     handlebars.register_template_string("tag_page", asset_data("pages/tag_page.html.hbs")?)?;
     handlebars.register_template_string("video_page", asset_data("pages/video_page.html.hbs")?)?;
 
     Ok(handlebars)
 }
 
-fn asset_binary_data(name: &str) -> anyhow::Result<Vec<u8>> {
-    let file = Asset::get(name).with_context(|| format!("missing static file {}", name))?;
-    Ok(file.data.into_owned())
-}
-
-fn asset_data(name: &str) -> anyhow::Result<String> {
-    let data = String::from_utf8(asset_binary_data(name)?)?;
-    Ok(data)
-}
-
 fn home_page_data<'a>(
     config: &'a Config,
     library: &'a Library,
+    playlists: &'a [Playlist],
 ) -> anyhow::Result<HomePageData<'a>> {
     let mut videos = Vec::with_capacity(library.videos.len());
 
     for library_video in &library.videos {
-        let restriction = library_video.restriction.as_ref();
+        let restrictions = &library_video.restrictions;
         let short_name = &library_video.video[0..config.thumbnail_hex_chars_prefix];
-        let tags = library_video
-            .tags
-            .iter()
-            .map(|tag| TagData {
-                name: tag.clone(),
-                clean_name: unidecode(tag)
-                    .chars()
-                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
-                    .collect(),
-            })
-            .collect_vec();
+        // The tags and thumbnail of hidden videos are only revealed by the front-end, after the
+        // access is decrypted
+        let tags = if library_video.hidden {
+            Vec::new()
+        } else {
+            library_video
+                .tags
+                .iter()
+                .map(|tag| TagData {
+                    name: tag.clone(),
+                    clean_name: clean_name(tag),
+                })
+                .collect_vec()
+        };
+        let thumbnail = if library_video.hidden {
+            "static/locked_thumbnail.svg".to_string()
+        } else {
+            format!("thumbnails/{}", library_video.thumbnail)
+        };
         let template_video = VideoData {
             tags,
             short_name,
-            thumbnail: format!("thumbnails/{}", library_video.thumbnail),
-            video: library_video
-                .restriction
-                .is_none()
-                .then_some(&library_video.video),
-            access_rule: restriction.map(|restriction| restriction.rule.as_str()),
-            access_iv: restriction.map(|restriction| restriction.iv.as_str()),
-            access_ciphertext: restriction.map(|restriction| restriction.ciphertext.as_str()),
+            thumbnail,
+            hidden: library_video.hidden,
+            video: restrictions.is_empty().then_some(&library_video.video),
+            access: if restrictions.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(&json!({
+                    "version": ACCESS_FORMAT_VERSION,
+                    "rules": restrictions,
+                }))?)
+            },
+            collections: restrictions
+                .iter()
+                .map(|restriction| CollectionData {
+                    name: &restriction.rule,
+                    page: collection_page(&restriction.rule),
+                })
+                .collect(),
+            playlists: playlists
+                .iter()
+                .filter(|playlist| {
+                    playlist
+                        .entries
+                        .iter()
+                        .any(|entry| entry.video == library_video.video)
+                })
+                .map(PlaylistLink::from)
+                .collect(),
             share_link: format!("video/{}.html", short_name),
         };
 
@@ -146,53 +953,255 @@ fn home_page_data<'a>(
         .cloned()
         .collect_vec();
 
-    let build_time = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)?
-        .as_secs();
-
     Ok(HomePageData {
-        build_time,
+        static_version: static_version()?,
+        collections: library
+            .collections
+            .iter()
+            .map(|collection| CollectionData {
+                name: &collection.rule,
+                page: collection_page(&collection.rule),
+            })
+            .collect(),
+        playlists: playlists.iter().map(PlaylistLink::from).collect(),
         all_tags,
-        access_salt: &config.file_access_salt,
-        access_iterations: config.file_access_iterations,
         videos,
+        neighbors: video_neighbors(&library.videos),
         thumbnail_height: config.thumbnail_height,
     })
 }
 
-#[derive(Embed)]
-#[folder = "web_src"]
-struct Asset;
+/// A hash of the static files, used instead of the build time to bust the browser cache, so that
+/// the pages only change when the static files do
+fn static_version() -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+    for item in static_asset_names()? {
+        hasher.update(item.as_bytes());
+        hasher.update(asset_binary_data(&item)?);
+    }
+    Ok(base16ct::lower::encode_string(&hasher.finalize()[0..8]))
+}
 
-#[derive(Debug, Serialize)]
+/// Split the videos into pages, with at least one page even when there are no videos
+fn paginate<T>(videos: &[T], page_size: usize) -> Vec<&[T]> {
+    if videos.is_empty() {
+        return vec![videos];
+    }
+    videos.chunks(page_size.max(1)).collect()
+}
+
+/// The navigation between the pages, or `None` when there is a single page
+fn pagination(
+    number: usize,
+    page_count: usize,
+    page_path: impl Fn(usize) -> String,
+) -> Option<Pagination> {
+    (page_count > 1).then(|| Pagination {
+        number,
+        page_count,
+        previous: (number > 1).then(|| page_path(number - 1)),
+        next: (number < page_count).then(|| page_path(number + 1)),
+    })
+}
+
+/// The path of the page of a playlist, relative to the root of the site
+fn playlist_page(name: &str) -> String {
+    format!("playlist/{}.html", clean_name(name))
+}
+
+/// The path of a page of the home page, relative to the root of the site. Pages are numbered from 1.
+fn home_page_path(number: usize) -> String {
+    if number == 1 {
+        "index.html".to_string()
+    } else {
+        format!("page/{}.html", number)
+    }
+}
+
+/// The path of a page of the tag page, relative to the root of the site. Pages are numbered from 1.
+fn tag_page_path(clean_name: &str, number: usize) -> String {
+    if number == 1 {
+        format!("tag/{}.html", clean_name)
+    } else {
+        format!("tag/{}/{}.html", clean_name, number)
+    }
+}
+
+/// The path of the feed of a tag, relative to the root of the site
+fn tag_feed_path(clean_name: &str) -> String {
+    format!("feed/{}.xml", clean_name)
+}
+
+/// The relative URL of the root of the site, from the page at `path`
+fn base_url(path: &str) -> String {
+    match path.matches('/').count() {
+        0 => ".".to_string(),
+        depth => vec![".."; depth].join("/"),
+    }
+}
+
+/// The path of the page of the collection of a rule, relative to the root of the site
+pub fn collection_page(rule: &str) -> String {
+    format!("collection/{}.html", clean_name(rule))
+}
+
+pub fn clean_name(tag: &str) -> String {
+    unidecode(tag)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+#[derive(Debug)]
 struct HomePageData<'a> {
-    build_time: u64,
-    access_salt: &'a str,
-    access_iterations: u32,
+    static_version: String,
+    collections: Vec<CollectionData<'a>>,
+    playlists: Vec<PlaylistLink<'a>>,
     videos: Vec<VideoData<'a>>,
     all_tags: Vec<TagData>,
+    /// The neighbors of each video, in the same order
+    neighbors: Vec<VideoNeighbors<'a>>,
+    thumbnail_height: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct IndexPageData<'a> {
+    static_version: &'a str,
+    collections: &'a [CollectionData<'a>],
+    playlists: &'a [PlaylistLink<'a>],
+    videos: &'a [VideoData<'a>],
     thumbnail_height: u32,
+    pagination: Option<Pagination>,
+    /// On the first page only
+    tag_cloud: Option<&'a [CloudTag<'a>]>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct Pagination {
+    number: usize,
+    page_count: usize,
+    previous: Option<String>,
+    next: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 struct VideoPageData<'a> {
     public_url: &'a str,
     page_title: String,
-    build_time: u64,
-    access_salt: &'a str,
-    access_iterations: u32,
+    static_version: &'a str,
     video: &'a VideoData<'a>,
     thumbnail_height: u32,
+    preview: Option<VideoPreview>,
+    by_date: NeighborLinks<'a>,
+    by_tag: Vec<TagNeighbors<'a>>,
+    related: Vec<&'a VideoData<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct NeighborLinks<'a> {
+    previous: Option<VideoLink<'a>>,
+    next: Option<VideoLink<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct VideoLink<'a> {
+    share_link: &'a str,
+    date: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TagNeighbors<'a> {
+    name: &'a str,
+    clean_name: String,
+    neighbors: NeighborLinks<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct VideoPreview {
+    description: String,
+    url: String,
+    image_url: String,
+    image_width: u32,
+    image_height: u32,
+    video_url: String,
+    video_type: Option<&'static str>,
+    width: u32,
+    height: u32,
+    duration_seconds: u64,
+    /// The schema.org `VideoObject`
+    json_ld: String,
+}
+
+/// The same page in another locale
+#[derive(Debug, Serialize)]
+struct Alternate {
+    lang: &'static str,
+    name: &'static str,
+    url: String,
+    /// The relative URL, from the current page
+    link: String,
+    current: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct SitemapData<'a> {
+    public_url: &'a str,
+    pages: &'a [String],
 }
 
 #[derive(Debug, Serialize)]
 struct TagPageData<'a> {
     selected_tag: &'a str,
     page_title: String,
-    build_time: u64,
-    access_salt: &'a str,
-    access_iterations: u32,
-    videos: Vec<&'a VideoData<'a>>,
+    feed: String,
+    static_version: &'a str,
+    videos: &'a [&'a VideoData<'a>],
+    pagination: Option<Pagination>,
+    summary: Option<&'a TagSummary<'a>>,
+}
+
+/// What the pages of all the locales show about the tags
+struct TagsData<'a> {
+    facet_definitions: &'a FacetDefinitions,
+    /// The facets of the filter page
+    facets: &'a [Facet],
+    stats: BTreeMap<&'a str, TagStats<'a>>,
+    cloud: Vec<CloudTag<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct CloudTag<'a> {
+    name: &'a str,
+    clean_name: String,
+    count: usize,
+    /// From 1 to `TAG_CLOUD_WEIGHTS`
+    weight: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct TagSummary<'a> {
+    count: usize,
+    first_date: Option<String>,
+    last_date: Option<String>,
+    duration: String,
+    /// The related tags of each declared facet, like the teachers or the events
+    facets: Vec<RelatedFacet<'a>>,
+    /// The other related tags
+    related: Vec<TagLink<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct RelatedFacet<'a> {
+    name: &'a str,
+    tags: Vec<TagLink<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct TagLink<'a> {
+    name: &'a str,
+    clean_name: String,
+    /// The number of videos shared with the tag of the page
+    count: usize,
 }
 
 #[derive(Debug, Serialize)]
@@ -200,15 +1209,202 @@ struct VideoData<'a> {
     tags: Vec<TagData>,
     short_name: &'a str,
     thumbnail: String,
+    hidden: bool,
     video: Option<&'a str>,
-    access_rule: Option<&'a str>,
-    access_iv: Option<&'a str>,
-    access_ciphertext: Option<&'a str>,
+    /// The JSON list of the encrypted accesses, one per rule
+    access: Option<String>,
+    /// The collections of the rules that grant access to the video
+    collections: Vec<CollectionData<'a>>,
+    /// The playlists that contain the video
+    playlists: Vec<PlaylistLink<'a>>,
     share_link: String,
 }
 
+#[derive(Debug, Serialize)]
+struct CollectionData<'a> {
+    name: &'a str,
+    page: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CollectionPageData<'a> {
+    page_title: String,
+    static_version: &'a str,
+    rule: &'a str,
+    description: Option<&'a str>,
+    videos: Vec<&'a VideoData<'a>>,
+    thumbnail_height: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct PlaylistLink<'a> {
+    title: &'a str,
+    page: String,
+}
+
+impl<'a> From<&'a Playlist> for PlaylistLink<'a> {
+    fn from(playlist: &'a Playlist) -> Self {
+        PlaylistLink {
+            title: &playlist.title,
+            page: playlist_page(&playlist.name),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PlaylistPageData<'a> {
+    page_title: String,
+    static_version: &'a str,
+    title: &'a str,
+    count: usize,
+    entries: Vec<PlaylistEntryData<'a>>,
+    thumbnail_height: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct PlaylistEntryData<'a> {
+    /// From 1
+    number: usize,
+    note: Option<&'a str>,
+    video: &'a VideoData<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct ArchivePageData<'a> {
+    static_version: &'a str,
+    years: &'a [ArchiveYear<&'a VideoData<'a>>],
+    undated_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ArchiveYearPageData<'a> {
+    page_title: String,
+    static_version: &'a str,
+    year: &'a ArchiveYear<&'a VideoData<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct ArchiveMonthPageData<'a> {
+    page_title: String,
+    static_version: &'a str,
+    month: &'a ArchiveMonth<&'a VideoData<'a>>,
+    previous: Option<ArchiveLink<'a>>,
+    next: Option<ArchiveLink<'a>>,
+    thumbnail_height: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct ArchiveLink<'a> {
+    name: &'a str,
+    page: &'a str,
+}
+
+impl<'a, V> From<&'a ArchiveMonth<V>> for ArchiveLink<'a> {
+    fn from(month: &'a ArchiveMonth<V>) -> Self {
+        ArchiveLink {
+            name: &month.name,
+            page: &month.page,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ArchiveUndatedPageData<'a> {
+    static_version: &'a str,
+    videos: &'a [&'a VideoData<'a>],
+    thumbnail_height: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct FeedData<'a> {
+    title: String,
+    feed_url: String,
+    page_url: String,
+    updated: String,
+    entries: Vec<FeedEntry<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct FeedEntry<'a> {
+    title: String,
+    url: String,
+    updated: String,
+    /// The file of public videos only
+    video_url: Option<String>,
+    thumbnail_url: String,
+    tags: Vec<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct FilterPageData<'a> {
+    static_version: &'a str,
+    facets: &'a [Facet],
+}
+
 #[derive(Debug, Serialize, Eq, PartialEq, Clone, Ord, PartialOrd, Hash)]
 struct TagData {
     name: String,
     clean_name: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate() {
+        let page_lengths = |count: usize| {
+            let videos = (0..count).collect_vec();
+            paginate(&videos, 3)
+                .iter()
+                .map(|page| page.len())
+                .collect_vec()
+        };
+
+        // An empty list still has its first page
+        assert_eq!(page_lengths(0), vec![0]);
+        assert_eq!(page_lengths(2), vec![2]);
+        assert_eq!(page_lengths(6), vec![3, 3]);
+        assert_eq!(page_lengths(7), vec![3, 3, 1]);
+    }
+
+    #[test]
+    fn test_pagination() {
+        assert_eq!(pagination(1, 1, home_page_path), None);
+        assert_eq!(
+            pagination(1, 3, home_page_path),
+            Some(Pagination {
+                number: 1,
+                page_count: 3,
+                previous: None,
+                next: Some("page/2.html".to_string()),
+            })
+        );
+        assert_eq!(
+            pagination(2, 3, home_page_path),
+            Some(Pagination {
+                number: 2,
+                page_count: 3,
+                previous: Some("index.html".to_string()),
+                next: Some("page/3.html".to_string()),
+            })
+        );
+        assert_eq!(
+            pagination(3, 3, |number| tag_page_path("swing-out", number)),
+            Some(Pagination {
+                number: 3,
+                page_count: 3,
+                previous: Some("tag/swing-out/2.html".to_string()),
+                next: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_page_paths() {
+        assert_eq!(home_page_path(1), "index.html");
+        assert_eq!(home_page_path(2), "page/2.html");
+        assert_eq!(tag_page_path("swing-out", 1), "tag/swing-out.html");
+        assert_eq!(tag_page_path("swing-out", 2), "tag/swing-out/2.html");
+    }
+}
@@ -1,34 +1,281 @@
+use crate::build::date::Date;
+use crate::build::password_strength::PasswordPolicy;
+use crate::build::tag_expression::TagExpression;
 use crate::tags_file::TagsVideo;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
 
 /// Declare all the access rules used to restrict the visibility of the videos
 #[derive(Debug, Deserialize, Default)]
 pub struct Restrictions {
+    #[serde(default)]
+    pub password_policy: PasswordPolicy,
     pub rules: Vec<RestrictionRule>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct RestrictionRule {
     pub name: String,
+    /// What the collection of videos of this rule is, shown on its page
+    pub description: Option<String>,
     #[serde(default)]
     pub with_tags: Vec<String>,
     #[serde(default)]
     pub without_tags: Vec<String>,
-    pub password: String,
+    /// A boolean expression the tags must also match, like `(stage AND 2024-*) OR "cours privé"`
+    pub expression: Option<TagExpression>,
+    /// The password in clear. Prefer keeping it out of this file, in the secret named `secret`
+    pub password: Option<String>,
+    /// The name of the secret that holds the password. Defaults to the name of the rule
+    pub secret: Option<String>,
+    /// Store the matching videos encrypted in the build, instead of only hiding their file name
+    #[serde(default)]
+    pub encrypt_videos: bool,
+    /// The first day the rule applies. Before it, the matching videos are not published
+    pub valid_from: Option<Date>,
+    /// The last day the rule applies. After it, the matching videos follow `then`
+    pub valid_until: Option<Date>,
+    #[serde(default)]
+    pub then: ExpiredBehavior,
+    #[serde(default)]
+    pub visibility: Visibility,
+}
+
+/// What is shown of the videos of a rule before the password is entered
+#[derive(Debug, Deserialize, Default, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// Their tags and thumbnail
+    #[default]
+    Visible,
+    /// Only a placeholder. Their tags and thumbnail are part of the encrypted payload, and they are
+    /// left out of the tag pages
+    Hidden,
+}
+
+/// What happens to the videos of a rule after its `valid_until`
+#[derive(Debug, Deserialize, Default, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiredBehavior {
+    /// Remove them from the build
+    #[default]
+    Remove,
+    /// Publish them without any restriction
+    Public,
+}
+
+/// How a video is published, considering all its rules at a given date
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum VideoStatus {
+    Public,
+    Restricted { rules: Vec<String> },
+    Removed,
+}
+
+/// Whether a rule applies at a given date
+#[derive(Debug, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleState {
+    /// Its videos are restricted
+    Active,
+    /// It expired and its videos are public
+    Public,
+    /// It is not valid yet, or it expired, and its videos are removed
+    Removed,
 }
 
 impl Restrictions {
-    pub fn find(&self, video: &TagsVideo) -> Option<&RestrictionRule> {
-        self.rules.iter().find(|rule| rule.matches(video))
+    /// Find all the rules that grant access to the video at the given date. Knowing the password of
+    /// any of them is enough to watch it.
+    pub fn find_all(&self, video: &TagsVideo, today: Date) -> Vec<&RestrictionRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(video) && rule.state(today) == RuleState::Active)
+            .collect()
+    }
+
+    /// Decide how the video is published at the given date. Any valid rule restricts it; otherwise
+    /// any rule that is not yet valid or that expired with [`ExpiredBehavior::Remove`] removes it.
+    pub fn status(&self, video: &TagsVideo, today: Date) -> VideoStatus {
+        let states = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(video))
+            .map(|rule| (rule, rule.state(today)))
+            .collect::<Vec<_>>();
+
+        let active_rules = states
+            .iter()
+            .filter(|(_, state)| *state == RuleState::Active)
+            .map(|(rule, _)| rule.name.clone())
+            .collect::<Vec<_>>();
+
+        if !active_rules.is_empty() {
+            VideoStatus::Restricted {
+                rules: active_rules,
+            }
+        } else if states.iter().any(|(_, state)| *state == RuleState::Removed) {
+            VideoStatus::Removed
+        } else {
+            VideoStatus::Public
+        }
+    }
+
+    /// Warn about the rules that will expire in the next `warning_days`
+    pub fn warn_expiring(&self, today: Date, warning_days: u32) {
+        for rule in &self.rules {
+            if let Some(valid_until) = rule.valid_until {
+                let remaining = valid_until.days_since_epoch() - today.days_since_epoch();
+                if (0..=i64::from(warning_days)).contains(&remaining) {
+                    let then = match rule.then {
+                        ExpiredBehavior::Remove => "removed",
+                        ExpiredBehavior::Public => "public",
+                    };
+                    log::warn!(
+                        "Rule {} expires in {} days, on {}: its videos will then be {}",
+                        rule.name,
+                        remaining,
+                        valid_until,
+                        then
+                    );
+                }
+            }
+        }
     }
 }
 
 impl RestrictionRule {
-    fn matches(&self, video: &TagsVideo) -> bool {
+    /// The name of the secret that holds the password, when it is not in clear
+    pub fn secret_name(&self) -> &str {
+        self.secret.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn state(&self, today: Date) -> RuleState {
+        if self.valid_from.is_some_and(|valid_from| today < valid_from) {
+            RuleState::Removed
+        } else if self
+            .valid_until
+            .is_some_and(|valid_until| today > valid_until)
+        {
+            match self.then {
+                ExpiredBehavior::Remove => RuleState::Removed,
+                ExpiredBehavior::Public => RuleState::Public,
+            }
+        } else {
+            RuleState::Active
+        }
+    }
+
+    pub fn matches(&self, video: &TagsVideo) -> bool {
         self.with_tags.iter().all(|tag| video.tags.contains(tag))
             && self
                 .without_tags
                 .iter()
                 .all(|tag| !video.tags.contains(tag))
+            && self
+                .expression
+                .as_ref()
+                .is_none_or(|expression| expression.matches(&video.tags))
+    }
+
+    /// Whether the tag is literally one of the tags that select the videos of the rule. Tags that
+    /// are only excluded, wildcard patterns and dates are not considered, since they are expected on
+    /// public videos too.
+    pub fn mentions_tag(&self, tag: &str) -> bool {
+        if tag.parse::<Date>().is_ok() {
+            return false;
+        }
+
+        self.with_tags.iter().any(|with_tag| with_tag == tag)
+            || self.expression.as_ref().is_some_and(|expression| {
+                expression
+                    .positive_patterns()
+                    .iter()
+                    .any(|pattern| pattern.literal() == Some(tag))
+            })
+    }
+}
+
+impl Display for RuleState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleState::Active => write!(f, "active"),
+            RuleState::Public => write!(f, "public"),
+            RuleState::Removed => write!(f, "removed"),
+        }
+    }
+}
+
+impl Display for VideoStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VideoStatus::Public => write!(f, "public"),
+            VideoStatus::Restricted { rules } => write!(f, "restricted by {}", rules.join(", ")),
+            VideoStatus::Removed => write!(f, "removed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        let restrictions: Restrictions = serde_json::from_str(
+            r#"{"rules": [
+                {"name": "camp", "with_tags": ["camp"], "password": "1234",
+                 "valid_from": "2024-01-01", "valid_until": "2024-06-30", "then": "public"},
+                {"name": "class", "with_tags": ["class"], "password": "5678",
+                 "valid_until": "2024-03-31"}
+            ]}"#,
+        )
+        .unwrap();
+        let video = |tags: &[&str]| TagsVideo {
+            name: "video.mp4".to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        };
+        let status =
+            |tags: &[&str], today: &str| restrictions.status(&video(tags), today.parse().unwrap());
+        let restricted = |rules: &[&str]| VideoStatus::Restricted {
+            rules: rules.iter().map(|rule| rule.to_string()).collect(),
+        };
+
+        assert_eq!(status(&["other"], "2024-02-01"), VideoStatus::Public);
+        assert_eq!(status(&["camp"], "2023-12-31"), VideoStatus::Removed);
+        assert_eq!(status(&["camp"], "2024-06-30"), restricted(&["camp"]));
+        assert_eq!(status(&["camp"], "2024-07-01"), VideoStatus::Public);
+        assert_eq!(status(&["class"], "2024-04-01"), VideoStatus::Removed);
+        assert_eq!(
+            status(&["camp", "class"], "2024-02-01"),
+            restricted(&["camp", "class"])
+        );
+        assert_eq!(
+            status(&["camp", "class"], "2024-05-01"),
+            restricted(&["camp"])
+        );
+        assert_eq!(
+            status(&["camp", "class"], "2024-07-01"),
+            VideoStatus::Removed
+        );
+    }
+
+    #[test]
+    fn test_mentions_tag() {
+        let restrictions: Restrictions = serde_json::from_str(
+            r#"{"rules": [
+                {"name": "camp", "with_tags": ["camp", "2024-05-01"],
+                 "expression": "(Alice OR 2024-*) AND NOT Bob", "password": "1234"}
+            ]}"#,
+        )
+        .unwrap();
+        let rule = &restrictions.rules[0];
+
+        assert!(rule.mentions_tag("camp"));
+        assert!(rule.mentions_tag("Alice"));
+        assert!(!rule.mentions_tag("Bob"));
+        assert!(!rule.mentions_tag("2024-05-01"));
+        assert!(!rule.mentions_tag("2024-06-01"));
     }
 }
@@ -0,0 +1,101 @@
+use crate::build::encrypt::{decrypt, derive_key, encrypt, random_key, random_salt, Encrypted};
+use crate::build::restrictions::Restrictions;
+use crate::config::Config;
+use crate::utils::maybe_read_string;
+use aes_gcm::{Aes256Gcm, Key};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+const RULE_KEYS_PATH: &str = "data/rule_keys.json";
+
+/// The version of the access format read by the front-end. Increase it on incompatible changes
+pub const ACCESS_FORMAT_VERSION: u32 = 1;
+
+/// The random content key of a rule, which encrypts the payloads of its videos
+#[derive(Debug)]
+pub struct RuleKey {
+    pub content_key: Key<Aes256Gcm>,
+    pub wrapped: WrappedKey,
+}
+
+/// The content key of a rule, encrypted with the key derived from its password. The front-end derives
+/// that key once per rule, unwraps the content key and can then cheaply decrypt every payload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WrappedKey {
+    pub salt: String,
+    pub iterations: u32,
+    pub key: Encrypted,
+}
+
+/// Read the content keys of the rules from `data/rule_keys.json`, creating the missing ones. A rule
+/// gets a new salt and content key when its password changes, so that the old password and the
+/// content keys unwrapped with it give no access to the new payloads.
+pub fn update_rule_keys(
+    config: &Config,
+    restrictions: &Restrictions,
+    passwords: &BTreeMap<String, String>,
+) -> anyhow::Result<BTreeMap<String, RuleKey>> {
+    let stored: BTreeMap<String, WrappedKey> = match maybe_read_string(RULE_KEYS_PATH)? {
+        None => BTreeMap::new(),
+        Some(data) => serde_json::from_str(&data).context("failed to parse rule keys")?,
+    };
+
+    let mut keys = BTreeMap::new();
+    for rule in &restrictions.rules {
+        let password = passwords.get(&rule.name).context("missing rule password")?;
+        let existing = stored
+            .get(&rule.name)
+            .filter(|wrapped| wrapped.iterations == config.file_access_iterations)
+            .and_then(|wrapped| unwrap_key(password, wrapped));
+
+        let key = match existing {
+            Some(key) => key,
+            None => {
+                log::info!("Create a new key for rule {}", rule.name);
+                new_key(password, config.file_access_iterations)?
+            }
+        };
+        keys.insert(rule.name.clone(), key);
+    }
+
+    let new_stored: BTreeMap<_, _> = keys
+        .iter()
+        .map(|(name, key)| (name, &key.wrapped))
+        .collect();
+    fs::write(RULE_KEYS_PATH, serde_json::to_string_pretty(&new_stored)?)?;
+
+    Ok(keys)
+}
+
+fn new_key(password: &str, iterations: u32) -> anyhow::Result<RuleKey> {
+    let content_key = random_key();
+    let salt = random_salt();
+    let password_key = derive_key(password, &salt, iterations);
+    let key = encrypt(&password_key, &base16ct::lower::encode_string(&content_key))?;
+
+    Ok(RuleKey {
+        content_key,
+        wrapped: WrappedKey {
+            salt,
+            iterations,
+            key,
+        },
+    })
+}
+
+/// Recover the content key, or `None` if the password changed
+fn unwrap_key(password: &str, wrapped: &WrappedKey) -> Option<RuleKey> {
+    let password_key = derive_key(password, &wrapped.salt, wrapped.iterations);
+    let content_key = decrypt(&password_key, &wrapped.key).ok()?;
+    let content_key: [u8; 32] = base16ct::lower::decode_vec(content_key)
+        .ok()?
+        .try_into()
+        .ok()?;
+
+    Some(RuleKey {
+        content_key: content_key.into(),
+        wrapped: wrapped.clone(),
+    })
+}
@@ -0,0 +1,178 @@
+use crate::build::date::Date;
+use crate::build::facets::{facets, Facet, FacetDefinitions};
+use crate::build::library::Library;
+use crate::build::render_pages::clean_name;
+use crate::config::Config;
+use itertools::Itertools;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use unidecode::unidecode;
+
+/// The version of the search index read by the front-end. Increase it on incompatible changes
+const SEARCH_INDEX_VERSION: u32 = 1;
+
+/// The index loaded by the search box, so that it can find videos without every card being on the
+/// page. Hidden videos are left out, since their tags must stay secret.
+#[derive(Debug, Serialize)]
+pub struct SearchIndex<'a> {
+    version: u32,
+    tags: Vec<SearchTag<'a>>,
+    videos: Vec<SearchVideo<'a>>,
+    /// The indexes in `tags` of the tags with each normalized trigram
+    tag_trigrams: BTreeMap<String, Vec<usize>>,
+    /// The indexes in `videos` of the videos whose tags or date have each normalized trigram
+    video_trigrams: BTreeMap<String, Vec<usize>>,
+    /// The facets of the filter page, whose values point into `videos`
+    pub facets: Vec<Facet>,
+    /// How `normalize()` folds each non-ASCII character of the tags, so that `js.mjs` folds the
+    /// searched text the same way, like `œ` into `oe`
+    folding: BTreeMap<char, String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SearchTag<'a> {
+    name: &'a str,
+    clean_name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SearchVideo<'a> {
+    short_name: &'a str,
+    title: String,
+    date: Option<Date>,
+    thumbnail: String,
+    /// The indexes in `tags`
+    tags: Vec<usize>,
+}
+
+pub fn search_index<'a>(
+    config: &Config,
+    library: &'a Library,
+    facet_definitions: &FacetDefinitions,
+) -> SearchIndex<'a> {
+    let visible_videos = library
+        .videos
+        .iter()
+        .filter(|video| !video.hidden)
+        .collect_vec();
+
+    let tag_names = visible_videos
+        .iter()
+        .flat_map(|video| &video.tags)
+        .unique()
+        .collect_vec();
+    let tag_indexes: BTreeMap<_, _> = tag_names
+        .iter()
+        .enumerate()
+        .map(|(index, &name)| (name, index))
+        .collect();
+
+    let mut tag_trigrams = BTreeMap::<_, Vec<_>>::new();
+    for (index, name) in tag_names.iter().enumerate() {
+        for trigram in trigrams(name) {
+            tag_trigrams.entry(trigram).or_default().push(index);
+        }
+    }
+
+    let mut videos = Vec::new();
+    let mut video_trigrams = BTreeMap::<_, Vec<_>>::new();
+    for (index, video) in visible_videos.iter().enumerate() {
+        let mut text = video.tags.join(" ");
+        if let Some(date) = video.date {
+            text.push(' ');
+            text.push_str(&date.to_string());
+        }
+        for trigram in trigrams(&text) {
+            video_trigrams.entry(trigram).or_default().push(index);
+        }
+
+        videos.push(SearchVideo {
+            short_name: &video.video[0..config.thumbnail_hex_chars_prefix],
+            title: video.tags.join(", "),
+            date: video.date,
+            thumbnail: format!("thumbnails/{}", video.thumbnail),
+            tags: video.tags.iter().map(|tag| tag_indexes[tag]).collect(),
+        });
+    }
+
+    let folding = tag_names
+        .iter()
+        .flat_map(|name| name.chars())
+        .filter(|c| !c.is_ascii())
+        .map(|c| (c, unidecode(&c.to_string())))
+        .collect();
+
+    SearchIndex {
+        version: SEARCH_INDEX_VERSION,
+        tags: tag_names
+            .into_iter()
+            .map(|name| SearchTag {
+                name,
+                clean_name: clean_name(name),
+            })
+            .collect(),
+        videos,
+        tag_trigrams,
+        video_trigrams,
+        facets: facets(facet_definitions, &visible_videos),
+        folding,
+    }
+}
+
+/// Lower case the text and remove the diacritics and punctuation, like `normalize()` in `js.mjs`
+fn normalize(text: &str) -> String {
+    unidecode(text)
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| {
+            if c.is_ascii_alphanumeric() {
+                Some(c)
+            } else if c.is_whitespace() {
+                Some(' ')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// The trigrams of each normalized word, or the word itself when it is shorter, like
+/// `getTrigrams()` in `js.mjs`
+fn trigrams(text: &str) -> BTreeSet<String> {
+    let mut trigrams = BTreeSet::new();
+    for word in normalize(text).split(' ').filter(|word| !word.is_empty()) {
+        if word.len() < 3 {
+            trigrams.insert(word.to_string());
+        } else {
+            for start in 0..word.len() - 2 {
+                trigrams.insert(word[start..start + 3].to_string());
+            }
+        }
+    }
+    trigrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigrams() {
+        assert_eq!(
+            trigrams("Swing-out  à Lyon"),
+            BTreeSet::from(
+                ["swi", "win", "ing", "ngo", "gou", "out", "a", "lyo", "yon"].map(String::from)
+            )
+        );
+        assert_eq!(
+            trigrams("2024-01-02"),
+            BTreeSet::from(["202", "024", "240", "401", "010", "102"].map(String::from))
+        );
+        assert_eq!(
+            trigrams("Œuvre Straße"),
+            BTreeSet::from(
+                ["oeu", "euv", "uvr", "vre", "str", "tra", "ras", "ass", "sse"].map(String::from)
+            )
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use crate::build::restrictions::{RestrictionRule, Restrictions};
+use crate::config::Config;
+use crate::utils::maybe_read_string;
+use anyhow::{bail, Context};
+use itertools::Itertools;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use unidecode::unidecode;
+
+const ENV_PREFIX: &str = "LINDY_HOP_SECRET_";
+
+/// The secrets stored in the file `SECRETS_PATH`, a JSON object from secret name to value. The file
+/// must only be readable by its owner.
+#[derive(Debug, Default)]
+pub struct SecretsFile {
+    secrets: BTreeMap<String, String>,
+}
+
+impl SecretsFile {
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let Some(data) = maybe_read_string(path)? else {
+            return Ok(SecretsFile::default());
+        };
+        check_permissions(path)?;
+
+        Ok(SecretsFile {
+            secrets: serde_json::from_str(&data)
+                .with_context(|| format!("failed to parse {}", path.display()))?,
+        })
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.secrets.insert(name.to_string(), value.to_string());
+    }
+
+    /// Write the file, only readable by its owner
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            // Created without access for the others, so that the secrets are never exposed
+            options.mode(0o600);
+        }
+        let mut file = options.open(path)?;
+        #[cfg(unix)]
+        {
+            // The mode only applies to a new file
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+
+        file.write_all(serde_json::to_string_pretty(&self.secrets)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)?.permissions().mode();
+    if mode & 0o077 != 0 {
+        bail!(
+            "{} can be accessed by other users (mode {:o}), fix it with `chmod 600 {}`",
+            path.display(),
+            mode & 0o777,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// The environment variable that can hold the secret with the given name
+fn secret_env_var(name: &str) -> String {
+    let suffix: String = unidecode(name)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}{}", ENV_PREFIX, suffix)
+}
+
+/// Resolve the password of every rule, from the rule itself, the environment or the secrets file.
+/// Fail with the list of all the missing secrets, so that no build is made without them.
+pub fn resolve_passwords(
+    config: &Config,
+    restrictions: &Restrictions,
+) -> anyhow::Result<BTreeMap<String, String>> {
+    let file = SecretsFile::read(Path::new(&config.secrets_path))?;
+
+    let mut passwords = BTreeMap::new();
+    let mut missing = Vec::new();
+    for rule in &restrictions.rules {
+        match find_password(rule, &file) {
+            Some(password) => {
+                passwords.insert(rule.name.clone(), password);
+            }
+            None => missing.push(rule),
+        }
+    }
+
+    if !missing.is_empty() {
+        bail!(
+            "missing the secrets of the rules {}. Set them with `restrictions set-password <rule>` \
+            or with the environment variables {}",
+            missing.iter().map(|rule| &rule.name).format(", "),
+            missing
+                .iter()
+                .map(|rule| secret_env_var(rule.secret_name()))
+                .format(", ")
+        );
+    }
+
+    Ok(passwords)
+}
+
+/// Resolve the password of a single rule, like [`resolve_passwords()`]
+pub fn resolve_password(config: &Config, rule: &RestrictionRule) -> anyhow::Result<String> {
+    let file = SecretsFile::read(Path::new(&config.secrets_path))?;
+    find_password(rule, &file).with_context(|| {
+        format!(
+            "missing the secret of the rule {}. Set it with `restrictions set-password` or with the \
+            environment variable {}",
+            rule.name,
+            secret_env_var(rule.secret_name())
+        )
+    })
+}
+
+fn find_password(rule: &RestrictionRule, file: &SecretsFile) -> Option<String> {
+    if let Some(password) = &rule.password {
+        log::warn!(
+            "Rule {} has its password in clear, prefer `restrictions set-password`",
+            rule.name
+        );
+        return Some(password.clone());
+    }
+
+    let name = rule.secret_name();
+    std::env::var(secret_env_var(name))
+        .ok()
+        .or_else(|| file.secrets.get(name).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lindy-hop-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn restrictions(rules: &str) -> Restrictions {
+        serde_json::from_str(&format!(r#"{{"rules": [{}]}}"#, rules)).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("secrets-permissions");
+        let path = dir.join("secrets.json");
+        let mut file = SecretsFile::default();
+        file.set("camp", "1234");
+        file.write(&path).unwrap();
+        assert!(check_permissions(&path).is_ok());
+        assert_eq!(SecretsFile::read(&path).unwrap().secrets["camp"], "1234");
+
+        for mode in [0o640, 0o604] {
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+            assert!(check_permissions(&path).is_err());
+            assert!(SecretsFile::read(&path).is_err());
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_secret_env_var() {
+        assert_eq!(secret_env_var("camp"), "LINDY_HOP_SECRET_CAMP");
+        assert_eq!(secret_env_var("Camp A"), "LINDY_HOP_SECRET_CAMP_A");
+        assert_eq!(secret_env_var("été-2024"), "LINDY_HOP_SECRET_ETE_2024");
+    }
+
+    #[test]
+    fn test_env_precedence() {
+        let restrictions = restrictions(r#"{"name": "Env rule", "secret": "env precedence"}"#);
+        let rule = &restrictions.rules[0];
+        let mut file = SecretsFile::default();
+        file.set("env precedence", "from file");
+        assert_eq!(find_password(rule, &file).unwrap(), "from file");
+
+        let var = secret_env_var("env precedence");
+        std::env::set_var(&var, "from env");
+        assert_eq!(find_password(rule, &file).unwrap(), "from env");
+        std::env::remove_var(&var);
+    }
+
+    #[test]
+    fn test_resolve_passwords() {
+        let dir = temp_dir("secrets-resolve");
+        let path = dir.join("secrets.json");
+        let mut file = SecretsFile::default();
+        file.set("stored", "1234");
+        file.write(&path).unwrap();
+        let config = Config {
+            file_access_iterations: 1,
+            page_size: 10,
+            public_url: String::new(),
+            restriction_expiry_warning_days: 0,
+            secrets_path: path.to_str().unwrap().to_string(),
+            thumbnail_height: 100,
+            thumbnail_hex_chars_prefix: 16,
+        };
+
+        let complete = restrictions(
+            r#"{"name": "Stored", "secret": "stored"},
+            {"name": "Clear", "password": "5678"}"#,
+        );
+        let passwords = resolve_passwords(&config, &complete).unwrap();
+        assert_eq!(passwords["Stored"], "1234");
+        assert_eq!(passwords["Clear"], "5678");
+
+        let incomplete = restrictions(
+            r#"{"name": "Stored", "secret": "stored"},
+            {"name": "First missing", "secret": "first missing"},
+            {"name": "Second missing", "secret": "second missing"}"#,
+        );
+        let error = resolve_passwords(&config, &incomplete)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("First missing, Second missing"));
+        assert!(error.contains("LINDY_HOP_SECRET_FIRST_MISSING, LINDY_HOP_SECRET_SECOND_MISSING"));
+        assert!(!error.contains("Stored"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+use crate::utils::list_dirs;
+use anyhow::Context;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The files of a build, staged in memory and then switched in at once. The new build is assembled
+/// in a sibling directory, where the files that did not change are hard links to the current ones,
+/// and then exchanged with the current build in a single rename. Someone browsing during the build
+/// sees either the old site or the new one, never a mix of both.
+#[derive(Debug)]
+pub struct StagedOutput {
+    root: PathBuf,
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    removed: BTreeSet<PathBuf>,
+    pruned_dirs: BTreeSet<PathBuf>,
+}
+
+impl StagedOutput {
+    /// Stage changes to the directory `root`. The paths of the files include it.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        StagedOutput {
+            root: root.into(),
+            files: BTreeMap::new(),
+            removed: BTreeSet::new(),
+            pruned_dirs: BTreeSet::new(),
+        }
+    }
+
+    pub fn write(&mut self, path: impl Into<PathBuf>, content: impl Into<Vec<u8>>) {
+        self.files.insert(path.into(), content.into());
+    }
+
+    pub fn remove(&mut self, path: impl Into<PathBuf>) {
+        self.removed.insert(path.into());
+    }
+
+    /// Remove the files of the directory and its subdirectories that are not staged
+    pub fn prune(&mut self, dir: impl Into<PathBuf>) {
+        self.pruned_dirs.insert(dir.into());
+    }
+
+    /// Assemble the new build, with the changed files written and the stale ones left out, then
+    /// switch it in
+    pub fn commit(self) -> anyhow::Result<()> {
+        let root = &self.root;
+        fs::create_dir_all(root)?;
+        let staging = sibling(root, "staging")?;
+        if fs::exists(&staging)? {
+            // Left by an interrupted build
+            fs::remove_dir_all(&staging)?;
+        }
+
+        let mut stale = Vec::new();
+        link_tree(root, &staging, &mut |path| {
+            if self.files.contains_key(path) {
+                // Staged again, handled below
+                false
+            } else if self.is_stale(path) {
+                stale.push(path.to_owned());
+                false
+            } else {
+                true
+            }
+        })
+        .context("failed to prepare the staging directory")?;
+
+        let mut written = 0;
+        for (path, content) in &self.files {
+            let target = staging.join(path.strip_prefix(root)?);
+            fs::create_dir_all(target.parent().context("missing parent dir")?)?;
+            if fs::read(path).is_ok_and(|existing| existing == *content) {
+                fs::hard_link(path, &target)?;
+            } else {
+                fs::write(&target, content)
+                    .with_context(|| format!("failed to write {}", target.display()))?;
+                written += 1;
+            }
+        }
+        self.remove_empty_pruned_dirs(&staging)?;
+
+        exchange_dirs(&staging, root)
+            .with_context(|| format!("failed to switch {} in", staging.display()))?;
+        // Now the previous build
+        fs::remove_dir_all(&staging)?;
+
+        for path in &stale {
+            log::info!("Removed {}", path.display());
+        }
+        log::info!(
+            "Wrote {} changed files, {} unchanged",
+            written,
+            self.files.len() - written
+        );
+
+        Ok(())
+    }
+
+    /// Remove the empty subdirectories of the pruned directories, left by their stale files
+    fn remove_empty_pruned_dirs(&self, staging: &Path) -> anyhow::Result<()> {
+        for dir in &self.pruned_dirs {
+            let staged_dir = staging.join(dir.strip_prefix(&self.root)?);
+            if fs::exists(&staged_dir)? {
+                remove_empty_dirs(&staged_dir)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_stale(&self, path: &Path) -> bool {
+        self.removed.contains(path) || self.pruned_dirs.iter().any(|dir| path.starts_with(dir))
+    }
+}
+
+/// A path next to `path`, like `build.staging` for `build`
+fn sibling(path: &Path, suffix: &str) -> anyhow::Result<PathBuf> {
+    let mut name = path.file_name().context("invalid build dir")?.to_owned();
+    name.push(".");
+    name.push(suffix);
+    Ok(path.with_file_name(name))
+}
+
+/// Recreate the tree of `source` into `destination` with hard links, which is cheap and leaves
+/// `source` untouched. Symbolic links are recreated as such, so that a directory stored elsewhere
+/// stays shared. Only the files for which `keep` returns `true` are linked.
+fn link_tree(
+    source: &Path,
+    destination: &Path,
+    keep: &mut impl FnMut(&Path) -> bool,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(destination)?;
+    for item in fs::read_dir(source)? {
+        let item = item?;
+        let path = item.path();
+        let target = destination.join(item.file_name());
+        let file_type = item.file_type()?;
+
+        if file_type.is_dir() {
+            link_tree(&path, &target, keep)?;
+        } else if !keep(&path) {
+            continue;
+        } else if file_type.is_symlink() {
+            symlink(&fs::read_link(&path)?, &target)?;
+        } else {
+            fs::hard_link(&path, &target)
+                .with_context(|| format!("failed to link {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(original: &Path, link: &Path) -> anyhow::Result<()> {
+    std::os::unix::fs::symlink(original, link)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn symlink(original: &Path, link: &Path) -> anyhow::Result<()> {
+    fs::copy(original, link)?;
+    Ok(())
+}
+
+/// Swap the contents of two paths in a single step, so that neither is ever seen missing
+#[cfg(target_os = "linux")]
+fn exchange_dirs(first: &Path, second: &Path) -> anyhow::Result<()> {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+
+    let first = CString::new(first.as_os_str().as_bytes())?;
+    let second = CString::new(second.as_os_str().as_bytes())?;
+    // SAFETY: both paths are valid NUL-terminated strings, that outlive the call
+    let result = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            first.as_ptr(),
+            libc::AT_FDCWD,
+            second.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Swap two paths with successive renames, since there is no atomic exchange on this platform
+#[cfg(not(target_os = "linux"))]
+fn exchange_dirs(first: &Path, second: &Path) -> anyhow::Result<()> {
+    let temporary = sibling(second, "previous")?;
+    fs::rename(second, &temporary)?;
+    fs::rename(first, second)?;
+    fs::rename(&temporary, first)?;
+    Ok(())
+}
+
+/// Remove the empty subdirectories left by the removed files
+fn remove_empty_dirs(dir: &Path) -> anyhow::Result<()> {
+    for subdir in list_dirs(dir)? {
+        remove_empty_dirs(&subdir)?;
+        if fs::read_dir(&subdir)?.next().is_none() {
+            fs::remove_dir(&subdir)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    fn test_commit() {
+        let dir = std::env::temp_dir().join(format!("lindy-hop-staged-{}", std::process::id()));
+        let root = dir.join("build");
+        fs::create_dir_all(root.join("videos")).unwrap();
+        fs::create_dir_all(root.join("tag/old")).unwrap();
+        fs::write(root.join("videos/a.mp4"), "video").unwrap();
+        fs::write(root.join("tag/old/2.html"), "stale").unwrap();
+        fs::write(root.join("index.html"), "same").unwrap();
+        fs::write(root.join("about.html"), "before").unwrap();
+        let index_inode = fs::metadata(root.join("index.html")).unwrap().ino();
+
+        let mut output = StagedOutput::new(&root);
+        output.prune(root.join("tag"));
+        output.write(root.join("index.html"), "same");
+        output.write(root.join("about.html"), "after");
+        output.write(root.join("tag/new.html"), "new");
+        output.commit().unwrap();
+
+        let read = |path: &str| fs::read_to_string(root.join(path)).unwrap();
+        assert_eq!(read("videos/a.mp4"), "video");
+        assert_eq!(read("index.html"), "same");
+        assert_eq!(read("about.html"), "after");
+        assert_eq!(read("tag/new.html"), "new");
+        assert!(!fs::exists(root.join("tag/old")).unwrap());
+        assert!(!fs::exists(dir.join("build.staging")).unwrap());
+        // Unchanged files are kept as they are
+        assert_eq!(
+            fs::metadata(root.join("index.html")).unwrap().ino(),
+            index_inode
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
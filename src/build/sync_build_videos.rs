@@ -1,25 +1,105 @@
+use crate::build::encrypt::{encrypt_file, encrypted_file_size, is_encrypted_with};
 use crate::utils::list_files;
+use aes_gcm::{Aes256Gcm, Key};
+use anyhow::Context;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::Path;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 
-pub fn sync_build_videos(source_dir: &Path, destination_dir: &Path) -> anyhow::Result<()> {
+/// Copy the videos into the build directory. The videos listed in `encryption_keys` are stored
+/// encrypted instead, with an added `.enc` extension, and their plain copy is removed. The `removed`
+/// videos are deleted from the build.
+pub fn sync_build_videos(
+    source_dir: &Path,
+    destination_dir: &Path,
+    encryption_keys: &BTreeMap<String, Key<Aes256Gcm>>,
+    removed: &BTreeSet<String>,
+) -> anyhow::Result<()> {
     fs::create_dir_all(destination_dir)?;
 
     let videos = list_files(source_dir)?;
 
     for source in videos {
-        let destination = destination_dir.join(source.strip_prefix(source_dir)?);
-        let source_size = fs::metadata(&source)?.len();
-        if !fs::exists(&destination)? {
-            fs::copy(source, destination)?;
-        } else {
-            let destination_size = fs::metadata(&destination)?.len();
-            if source_size != destination_size {
-                log::info!("Will overwrite {}", destination.display());
-                fs::copy(source, destination)?;
+        let name = source
+            .strip_prefix(source_dir)?
+            .to_str()
+            .context("invalid file name")?;
+        let destination = destination_dir.join(name);
+        let encrypted_destination = encrypted_path(&destination);
+
+        if removed.contains(name) {
+            remove_if_exists(&destination)?;
+            remove_if_exists(&encrypted_destination)?;
+            continue;
+        }
+
+        match encryption_keys.get(name) {
+            None => {
+                sync_plain_video(&source, &destination)?;
+                remove_if_exists(&encrypted_destination)?;
+            }
+            Some(key) => {
+                sync_encrypted_video(key, &source, &encrypted_destination)
+                    .with_context(|| format!("failed to encrypt {}", source.display()))?;
+                remove_if_exists(&destination)?;
             }
         }
     }
 
     Ok(())
 }
+
+/// The name of the encrypted version of a video
+pub fn encrypted_name(video: &str) -> String {
+    format!("{}.enc", video)
+}
+
+fn encrypted_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".enc");
+    PathBuf::from(path)
+}
+
+fn sync_plain_video(source: &Path, destination: &Path) -> anyhow::Result<()> {
+    let source_size = fs::metadata(source)?.len();
+    if !fs::exists(destination)? {
+        fs::copy(source, destination)?;
+    } else {
+        let destination_size = fs::metadata(destination)?.len();
+        if source_size != destination_size {
+            log::info!("Will overwrite {}", destination.display());
+            fs::copy(source, destination)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn sync_encrypted_video(
+    key: &Key<Aes256Gcm>,
+    source: &Path,
+    destination: &Path,
+) -> anyhow::Result<()> {
+    if fs::exists(destination)? {
+        let expected_size = encrypted_file_size(fs::metadata(source)?.len());
+        if fs::metadata(destination)?.len() == expected_size && is_encrypted_with(key, destination)?
+        {
+            return Ok(());
+        }
+    }
+
+    log::info!("Will encrypt into {}", destination.display());
+    encrypt_file(key, source, destination)
+}
+
+fn remove_if_exists(path: &Path) -> anyhow::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => {
+            log::info!("Removed {}", path.display());
+            Ok(())
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
@@ -0,0 +1,389 @@
+use anyhow::{bail, ensure};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::FromStr;
+use std::vec::IntoIter;
+
+/// A boolean expression over the tags of a video, like `(stage AND 2024) OR "cours privé"`.
+///
+/// Tags are bare words or quoted strings, and `*` matches any sequence of characters, like in
+/// `"Camp *"` or `2023-*`. `NOT` binds tighter than `AND`, which binds tighter than `OR`.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TagExpression {
+    Tag(TagPattern),
+    Not(Box<TagExpression>),
+    And(Box<TagExpression>, Box<TagExpression>),
+    Or(Box<TagExpression>, Box<TagExpression>),
+}
+
+/// A tag, where `*` matches any sequence of characters
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(from = "String")]
+pub struct TagPattern(String);
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Tag(String),
+}
+
+impl TagExpression {
+    pub fn matches(&self, tags: &[String]) -> bool {
+        match self {
+            TagExpression::Tag(pattern) => tags.iter().any(|tag| pattern.matches(tag)),
+            TagExpression::Not(inner) => !inner.matches(tags),
+            TagExpression::And(left, right) => left.matches(tags) && right.matches(tags),
+            TagExpression::Or(left, right) => left.matches(tags) || right.matches(tags),
+        }
+    }
+
+    /// The patterns that select videos, as opposed to the ones under a `NOT`
+    pub fn positive_patterns(&self) -> Vec<&TagPattern> {
+        fn collect<'a>(
+            expression: &'a TagExpression,
+            negated: bool,
+            into: &mut Vec<&'a TagPattern>,
+        ) {
+            match expression {
+                TagExpression::Tag(pattern) => {
+                    if !negated {
+                        into.push(pattern);
+                    }
+                }
+                TagExpression::Not(inner) => collect(inner, !negated, into),
+                TagExpression::And(left, right) | TagExpression::Or(left, right) => {
+                    collect(left, negated, into);
+                    collect(right, negated, into);
+                }
+            }
+        }
+
+        let mut patterns = Vec::new();
+        collect(self, false, &mut patterns);
+        patterns
+    }
+}
+
+impl TagPattern {
+    /// The tag itself, if the pattern has no wildcard
+    pub fn literal(&self) -> Option<&str> {
+        (!self.0.contains('*')).then_some(self.0.as_str())
+    }
+
+    pub fn matches(&self, tag: &str) -> bool {
+        let mut parts = self.0.split('*');
+        let first = parts.next().unwrap_or_default();
+        let Some(mut rest) = tag.strip_prefix(first) else {
+            return false;
+        };
+
+        let mut parts: Vec<_> = parts.collect();
+        let Some(last) = parts.pop() else {
+            // No wildcard
+            return rest.is_empty();
+        };
+
+        for part in parts {
+            match rest.find(part) {
+                None => return false,
+                Some(index) => rest = &rest[index + part.len()..],
+            }
+        }
+
+        rest.ends_with(last)
+    }
+}
+
+impl From<String> for TagPattern {
+    fn from(pattern: String) -> Self {
+        TagPattern(pattern)
+    }
+}
+
+impl FromStr for TagExpression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        ensure!(!tokens.is_empty(), "empty tag expression");
+
+        let mut parser = Parser {
+            source: s,
+            tokens: tokens.into_iter().peekable(),
+        };
+        let expression = parser.parse_or()?;
+        if let Some((token, column)) = parser.tokens.next() {
+            bail!(
+                "unexpected {} at column {} in {:?}",
+                token,
+                column,
+                parser.source
+            );
+        }
+
+        Ok(expression)
+    }
+}
+
+impl TryFrom<String> for TagExpression {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Split the expression into tokens, each with its 1-based column
+fn tokenize(s: &str) -> anyhow::Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().enumerate().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        let column = index + 1;
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push((Token::Open, column)),
+            ')' => tokens.push((Token::Close, column)),
+            '"' => {
+                let mut tag = String::new();
+                loop {
+                    match chars.next() {
+                        None => bail!("unterminated quote at column {} in {:?}", column, s),
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => tag.push(escaped),
+                            None => bail!("unterminated quote at column {} in {:?}", column, s),
+                        },
+                        Some((_, c)) => tag.push(c),
+                    }
+                }
+                ensure!(!tag.is_empty(), "empty tag at column {} in {:?}", column, s);
+                tokens.push((Token::Tag(tag), column));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = chars.next_if(|(_, c)| !is_delimiter(*c)) {
+                    word.push(c);
+                }
+                let token = match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Tag(word),
+                };
+                tokens.push((token, column));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || c == '(' || c == ')' || c == '"'
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Peekable<IntoIter<(Token, usize)>>,
+}
+
+impl Parser<'_> {
+    fn parse_or(&mut self) -> anyhow::Result<TagExpression> {
+        let mut expression = self.parse_and()?;
+        while self
+            .tokens
+            .next_if(|(token, _)| *token == Token::Or)
+            .is_some()
+        {
+            let right = self.parse_and()?;
+            expression = TagExpression::Or(Box::new(expression), Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> anyhow::Result<TagExpression> {
+        let mut expression = self.parse_not()?;
+        while self
+            .tokens
+            .next_if(|(token, _)| *token == Token::And)
+            .is_some()
+        {
+            let right = self.parse_not()?;
+            expression = TagExpression::And(Box::new(expression), Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    fn parse_not(&mut self) -> anyhow::Result<TagExpression> {
+        if self
+            .tokens
+            .next_if(|(token, _)| *token == Token::Not)
+            .is_some()
+        {
+            return Ok(TagExpression::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> anyhow::Result<TagExpression> {
+        match self.tokens.next() {
+            Some((Token::Tag(tag), _)) => Ok(TagExpression::Tag(TagPattern(tag))),
+            Some((Token::Open, column)) => {
+                let expression = self.parse_or()?;
+                match self.tokens.next() {
+                    Some((Token::Close, _)) => Ok(expression),
+                    Some((token, close_column)) => bail!(
+                        "expected ) to close the ( at column {}, found {} at column {} in {:?}",
+                        column,
+                        token,
+                        close_column,
+                        self.source
+                    ),
+                    None => bail!(
+                        "missing ) to close the ( at column {} in {:?}",
+                        column,
+                        self.source
+                    ),
+                }
+            }
+            Some((token, column)) => bail!(
+                "expected a tag, NOT or (, found {} at column {} in {:?}",
+                token,
+                column,
+                self.source
+            ),
+            None => bail!("expected a tag, NOT or ( at the end of {:?}", self.source),
+        }
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::Not => write!(f, "NOT"),
+            Token::Tag(tag) => write!(f, "tag {:?}", tag),
+        }
+    }
+}
+
+impl Display for TagPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let is_bare = !self.0.is_empty()
+            && !self.0.chars().any(is_delimiter)
+            && !["AND", "OR", "NOT"].contains(&self.0.as_str());
+        if is_bare {
+            write!(f, "{}", self.0)
+        } else {
+            write!(f, "{:?}", self.0)
+        }
+    }
+}
+
+impl Display for TagExpression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagExpression::Tag(pattern) => write!(f, "{}", pattern),
+            TagExpression::Not(inner) => match **inner {
+                TagExpression::And(..) | TagExpression::Or(..) => write!(f, "NOT ({})", inner),
+                _ => write!(f, "NOT {}", inner),
+            },
+            TagExpression::And(left, right) => {
+                for (index, side) in [left, right].into_iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " AND ")?;
+                    }
+                    match **side {
+                        TagExpression::Or(..) => write!(f, "({})", side)?,
+                        _ => write!(f, "{}", side)?,
+                    }
+                }
+                Ok(())
+            }
+            TagExpression::Or(left, right) => write!(f, "{} OR {}", left, right),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+        let expression: TagExpression = r#"(stage AND 2024-*) OR "cours privé" AND NOT "Camp *""#
+            .parse()
+            .unwrap();
+
+        assert!(expression.matches(&tags(&["stage", "2024-03-09"])));
+        assert!(expression.matches(&tags(&["cours privé"])));
+        assert!(!expression.matches(&tags(&["stage", "2023-03-09"])));
+        assert!(!expression.matches(&tags(&["cours privé", "Camp Hiver"])));
+        assert!(!expression.matches(&tags(&[])));
+
+        let positive = expression.positive_patterns();
+        assert_eq!(
+            positive
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect::<Vec<_>>(),
+            ["stage", "2024-*", r#""cours privé""#]
+        );
+    }
+
+    #[test]
+    fn test_pattern() {
+        let pattern = |pattern: &str| TagPattern(pattern.to_string());
+
+        assert!(pattern("Camp *").matches("Camp Hiver"));
+        assert!(!pattern("Camp *").matches("Camping"));
+        assert!(pattern("*a*b*").matches("xaxbx"));
+        assert!(!pattern("*a*b").matches("xbxa"));
+        assert!(pattern("2023").matches("2023"));
+        assert!(!pattern("2023").matches("2023-01-01"));
+    }
+
+    #[test]
+    fn test_display() {
+        let expression: TagExpression = r#"NOT (a OR "b c") AND (d OR e) OR f"#.parse().unwrap();
+        assert_eq!(
+            expression.to_string(),
+            r#"NOT (a OR "b c") AND (d OR e) OR f"#
+        );
+        assert_eq!(
+            expression.to_string().parse::<TagExpression>().unwrap(),
+            expression
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |s: &str| s.parse::<TagExpression>().unwrap_err().to_string();
+
+        assert_eq!(error(""), "empty tag expression");
+        assert_eq!(
+            error("a AND"),
+            r#"expected a tag, NOT or ( at the end of "a AND""#
+        );
+        assert_eq!(
+            error("(a OR b"),
+            r#"missing ) to close the ( at column 1 in "(a OR b""#
+        );
+        assert_eq!(error("a b"), r#"unexpected tag "b" at column 3 in "a b""#);
+        assert_eq!(
+            error(r#"a OR "b"#),
+            r#"unterminated quote at column 6 in "a OR \"b""#
+        );
+    }
+}
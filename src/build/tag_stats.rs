@@ -0,0 +1,120 @@
+use crate::build::date::Date;
+use crate::build::library::LibraryVideo;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// What the videos of a tag have in common
+#[derive(Debug, PartialEq)]
+pub struct TagStats<'a> {
+    pub count: usize,
+    pub first_date: Option<Date>,
+    pub last_date: Option<Date>,
+    pub duration_seconds: f64,
+    /// The other tags of its videos with how many videos they share, from the most shared one.
+    /// Dates are left out.
+    pub related: Vec<(&'a str, usize)>,
+}
+
+/// Compute the statistics of each tag of the videos
+pub fn tag_stats<'a>(videos: &[&'a LibraryVideo]) -> BTreeMap<&'a str, TagStats<'a>> {
+    // The related tags are counted next to the statistics they belong to, then sorted
+    let mut stats = BTreeMap::<&str, (TagStats, BTreeMap<&str, usize>)>::new();
+
+    for video in videos {
+        let date = video.date.map(|date| date.to_string());
+        for tag in &video.tags {
+            let (tag_stats, tag_related) = stats.entry(tag).or_insert_with(|| {
+                let tag_stats = TagStats {
+                    count: 0,
+                    first_date: None,
+                    last_date: None,
+                    duration_seconds: 0.,
+                    related: Vec::new(),
+                };
+                (tag_stats, BTreeMap::new())
+            });
+            tag_stats.count += 1;
+            if let Some(metadata) = video.metadata {
+                tag_stats.duration_seconds += metadata.duration_seconds;
+            }
+            if let Some(date) = video.date {
+                tag_stats.first_date =
+                    Some(tag_stats.first_date.map_or(date, |other| other.min(date)));
+                tag_stats.last_date =
+                    Some(tag_stats.last_date.map_or(date, |other| other.max(date)));
+            }
+
+            for other in &video.tags {
+                if other != tag && date.as_ref() != Some(other) {
+                    *tag_related.entry(other).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    stats
+        .into_iter()
+        .map(|(tag, (mut tag_stats, tag_related))| {
+            tag_stats.related = tag_related.into_iter().collect();
+            tag_stats.related.sort_by_key(|&(_, count)| Reverse(count));
+            (tag, tag_stats)
+        })
+        .collect()
+}
+
+/// Format a duration like "2 h 05 min", "12 min" or "40 s"
+pub fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match (seconds / 3600, seconds / 60 % 60) {
+        (0, 0) => format!("{} s", seconds),
+        (0, minutes) => format!("{} min", minutes),
+        (hours, minutes) => format!("{} h {:02} min", hours, minutes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_stats() {
+        let videos = [
+            LibraryVideo::for_test(
+                "a",
+                "2024-03-02",
+                &["2024-03-02", "Alice", "swing out"],
+                false,
+            ),
+            LibraryVideo::for_test(
+                "b",
+                "2023-05-01",
+                &["2023-05-01", "Alice", "Bob", "swing out"],
+                false,
+            ),
+            LibraryVideo::for_test("c", "", &["Alice", "Bob"], false),
+        ];
+        let stats = tag_stats(&videos.iter().collect::<Vec<_>>());
+
+        assert_eq!(
+            stats["Alice"],
+            TagStats {
+                count: 3,
+                first_date: "2023-05-01".parse().ok(),
+                last_date: "2024-03-02".parse().ok(),
+                duration_seconds: 90.,
+                related: vec![("Bob", 2), ("swing out", 2)],
+            }
+        );
+        assert_eq!(
+            stats["2024-03-02"].related,
+            vec![("Alice", 1), ("swing out", 1)]
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(40.4), "40 s");
+        assert_eq!(format_duration(725.), "12 min");
+        assert_eq!(format_duration(7500.), "2 h 05 min");
+    }
+}
@@ -8,50 +8,75 @@ use std::fs;
 use std::path::Path;
 use std::process::{Command, Stdio};
 
-/// Create the necessary thumbnails, returning a mapping from video name to thumbnail name
+/// Create the necessary thumbnails and remove the unused ones, returning a mapping from video name to
+/// thumbnail name. The thumbnails of the videos in `secret_names` are stored with that name, instead
+/// of the public prefix of the video hash.
 pub fn update_thumbnails(
     config: &Config,
     videos_dir: &Path,
-    videos: &[TagsVideo],
+    videos: &[&TagsVideo],
+    secret_names: &BTreeMap<String, String>,
 ) -> anyhow::Result<BTreeMap<String, String>> {
     let mut mapping = BTreeMap::new();
     let thumbnail_dir = Path::new("build/thumbnails");
     fs::create_dir_all(thumbnail_dir)?;
     let existing_thumbnails = list_files(thumbnail_dir)?;
-    let mut existing_thumbnail_hashes = BTreeSet::new();
+    let mut unused_thumbnail_stems = BTreeSet::new();
     for thumbnail in &existing_thumbnails {
-        let hash = thumbnail
+        let stem = thumbnail
             .file_stem()
             .and_then(|stem| stem.to_str())
             .context("failed to get file stem")?;
 
-        existing_thumbnail_hashes.insert(hash);
+        unused_thumbnail_stems.insert(stem.to_string());
     }
 
-    let mut missing_thumbnails = Vec::new();
+    let mut thumbnail_stems = Vec::with_capacity(videos.len());
     for video in videos {
         let video_hash = video
             .name
             .rsplit_once('.')
             .context("failed to get file stem")?
             .0;
-        let thumbnail_hash = &video_hash[0..config.thumbnail_hex_chars_prefix];
+        let public_stem = &video_hash[0..config.thumbnail_hex_chars_prefix];
+        let stem = secret_names
+            .get(&video.name)
+            .map_or(public_stem, |name| name.as_str());
+
+        mapping.insert(video.name.clone(), format!("{}.webp", stem));
+        thumbnail_stems.push((video, public_stem, stem));
+    }
 
-        let thumbnail_name = format!("{}.webp", thumbnail_hash);
+    let mut missing_thumbnails = Vec::new();
+    for (video, public_stem, stem) in thumbnail_stems {
+        let thumbnail_path = thumbnail_dir.join(format!("{}.webp", stem));
+        if unused_thumbnail_stems.remove(stem) {
+            continue;
+        }
 
-        mapping.insert(video.name.clone(), thumbnail_name.clone());
-        if !existing_thumbnail_hashes.contains(&thumbnail_hash) {
-            missing_thumbnails.push((video, thumbnail_name));
+        // The video was public before: reuse its thumbnail instead of extracting it again
+        if unused_thumbnail_stems.remove(public_stem) {
+            let public_path = thumbnail_dir.join(format!("{}.webp", public_stem));
+            log::info!("Rename thumbnail {}", public_path.display());
+            fs::rename(public_path, thumbnail_path)?;
+            continue;
         }
+
+        missing_thumbnails.push((video, thumbnail_path));
+    }
+
+    for unused_stem in unused_thumbnail_stems {
+        let path = thumbnail_dir.join(format!("{}.webp", unused_stem));
+        log::info!("Remove unused thumbnail {}", path.display());
+        fs::remove_file(path)?;
     }
 
     log::info!("Will update {} new thumbnails", missing_thumbnails.len());
-    for (video, thumbnail_name) in missing_thumbnails {
+    for (video, thumbnail_path) in missing_thumbnails {
         let video_path = videos_dir.join(&video.name);
         let duration_s = measure_duration_s(&video_path).context("failed to get duration")?;
 
         let thumbnail_position_s = duration_s / 2.0;
-        let thumbnail_path = thumbnail_dir.join(thumbnail_name);
         log::info!(
             "Will extract thumbnail at {} into {}",
             thumbnail_position_s,
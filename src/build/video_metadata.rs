@@ -0,0 +1,112 @@
+use crate::tags_file::TagsVideo;
+use crate::utils::maybe_read_string;
+use anyhow::{ensure, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const VIDEO_METADATA_PATH: &str = "data/video_metadata.json";
+
+/// The properties of a video file shown in the previews of the video pages
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VideoMetadata {
+    pub width: u32,
+    pub height: u32,
+    pub duration_seconds: f64,
+}
+
+/// Read the metadata of the videos from `data/video_metadata.json`, probing the new videos with
+/// `ffprobe` and forgetting the others. A video that cannot be probed is left out, so it just gets
+/// no preview, and is probed again by the next build.
+pub fn update_video_metadata(
+    videos_dir: &Path,
+    videos: &[&TagsVideo],
+) -> anyhow::Result<BTreeMap<String, VideoMetadata>> {
+    let stored: BTreeMap<String, _> = match maybe_read_string(VIDEO_METADATA_PATH)? {
+        None => BTreeMap::new(),
+        Some(data) => serde_json::from_str(&data)
+            .with_context(|| format!("failed to parse {}", VIDEO_METADATA_PATH))?,
+    };
+
+    let mut metadata = BTreeMap::new();
+    for video in videos {
+        let video_metadata = match stored.get(&video.name) {
+            Some(&video_metadata) => video_metadata,
+            None => {
+                log::info!("Probe metadata of {}", video.name);
+                match probe_metadata(&videos_dir.join(&video.name)) {
+                    Ok(video_metadata) => video_metadata,
+                    Err(error) => {
+                        log::warn!("Failed to probe {}: {:#}", video.name, error);
+                        continue;
+                    }
+                }
+            }
+        };
+        metadata.insert(video.name.clone(), video_metadata);
+    }
+
+    if metadata != stored {
+        fs::write(
+            VIDEO_METADATA_PATH,
+            serde_json::to_string_pretty(&metadata)?,
+        )?;
+    }
+
+    Ok(metadata)
+}
+
+fn probe_metadata(video: &Path) -> anyhow::Result<VideoMetadata> {
+    #[derive(Debug, Deserialize)]
+    struct OutputJson {
+        streams: Vec<OutputJsonStream>,
+        format: OutputJsonFormat,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct OutputJsonStream {
+        codec_type: String,
+        width: Option<u32>,
+        height: Option<u32>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct OutputJsonFormat {
+        duration: String,
+    }
+
+    let output = Command::new("ffprobe")
+        .args([
+            "-of",
+            "json",
+            "-show_entries",
+            "stream=width,height,codec_type:format=duration",
+        ])
+        .arg(video)
+        .output()
+        .context("failed to execute ffprobe")?;
+
+    ensure!(
+        output.status.success(),
+        "ffprobe returned a non-zero exit code"
+    );
+
+    let data: OutputJson = serde_json::from_slice(&output.stdout)?;
+    let video_stream = data
+        .streams
+        .into_iter()
+        .find(|stream| stream.codec_type == "video")
+        .context("missing video stream")?;
+
+    let width = video_stream.width.context("missing width")?;
+    let height = video_stream.height.context("missing height")?;
+    ensure!(width > 0 && height > 0, "invalid size {}x{}", width, height);
+
+    Ok(VideoMetadata {
+        width,
+        height,
+        duration_seconds: data.format.duration.parse().context("invalid duration")?,
+    })
+}
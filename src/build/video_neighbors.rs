@@ -0,0 +1,178 @@
+use crate::build::date::Date;
+use crate::build::library::LibraryVideo;
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// The number of related videos suggested on the page of a video
+const RELATED_VIDEOS: usize = 6;
+
+/// The videos suggested by the page of a video
+#[derive(Debug, PartialEq, Default)]
+pub struct VideoNeighbors<'a> {
+    /// The closest dated videos, in date order
+    pub by_date: Neighbors<'a>,
+    /// The closest videos of each tag of the video that has others, in date order with the undated
+    /// videos first
+    pub by_tag: Vec<(&'a str, Neighbors<'a>)>,
+    /// The videos with the most tags in common, dates left out, then the closest in date
+    pub related: Vec<&'a LibraryVideo>,
+}
+
+#[derive(Debug, PartialEq, Default)]
+pub struct Neighbors<'a> {
+    pub previous: Option<&'a LibraryVideo>,
+    pub next: Option<&'a LibraryVideo>,
+}
+
+/// Compute the neighbors of each video, in the same order. Hidden videos are never suggested and
+/// have no neighbors, since their tags and date must stay secret.
+pub fn video_neighbors(videos: &[LibraryVideo]) -> Vec<VideoNeighbors> {
+    let mut chronological = videos.iter().filter(|video| !video.hidden).collect_vec();
+    chronological.sort_by_key(|video| (video.date, &video.video));
+
+    let dated = chronological
+        .iter()
+        .copied()
+        .filter(|video| video.date.is_some())
+        .collect_vec();
+    let mut by_tag = BTreeMap::<&str, Vec<&LibraryVideo>>::new();
+    for &video in &chronological {
+        for tag in &video.tags {
+            by_tag.entry(tag).or_default().push(video);
+        }
+    }
+    // Only the tags that are not dates count for the related videos
+    let topic_tags = chronological
+        .iter()
+        .map(|video| {
+            let tags = video
+                .tags
+                .iter()
+                .filter(|tag| tag.parse::<Date>().is_err())
+                .map(String::as_str)
+                .collect_vec();
+            (video.video.as_str(), tags)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    videos
+        .iter()
+        .map(|video| {
+            if video.hidden {
+                return VideoNeighbors::default();
+            }
+
+            let by_tag = video
+                .tags
+                .iter()
+                .filter_map(|tag| {
+                    let tag_videos = &by_tag[tag.as_str()];
+                    (tag_videos.len() > 1).then(|| (tag.as_str(), neighbors(tag_videos, video)))
+                })
+                .collect_vec();
+
+            let tags = &topic_tags[video.video.as_str()];
+            let mut related = chronological
+                .iter()
+                .filter(|other| other.video != video.video)
+                .filter_map(|&other| {
+                    let shared = topic_tags[other.video.as_str()]
+                        .iter()
+                        .filter(|tag| tags.contains(tag))
+                        .count();
+                    let distance = match (video.date, other.date) {
+                        (Some(date), Some(other_date)) => {
+                            (date.days_since_epoch() - other_date.days_since_epoch()).abs()
+                        }
+                        _ => i64::MAX,
+                    };
+                    (shared > 0).then_some((Reverse(shared), distance, other))
+                })
+                .collect_vec();
+            related.sort_by_key(|&(shared, distance, other)| (shared, distance, &other.video));
+
+            VideoNeighbors {
+                by_date: neighbors(&dated, video),
+                by_tag,
+                related: related
+                    .into_iter()
+                    .take(RELATED_VIDEOS)
+                    .map(|(_, _, other)| other)
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// The videos around `video` in `sequence`, if it is part of it
+fn neighbors<'a>(sequence: &[&'a LibraryVideo], video: &LibraryVideo) -> Neighbors<'a> {
+    match sequence.iter().position(|other| other.video == video.video) {
+        None => Neighbors::default(),
+        Some(index) => Neighbors {
+            previous: index.checked_sub(1).map(|index| sequence[index]),
+            next: sequence.get(index + 1).copied(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(videos: &[&LibraryVideo]) -> Vec<String> {
+        videos.iter().map(|video| video.video.clone()).collect()
+    }
+
+    #[test]
+    fn test_video_neighbors() {
+        let videos = [
+            LibraryVideo::for_test(
+                "d",
+                "2024-03-01",
+                &["2024-03-01", "Alice", "swing out"],
+                false,
+            ),
+            LibraryVideo::for_test("c", "2024-02-01", &["2024-02-01", "Alice", "Bob"], true),
+            LibraryVideo::for_test(
+                "b",
+                "2024-01-01",
+                &["2024-01-01", "Bob", "swing out"],
+                false,
+            ),
+            LibraryVideo::for_test(
+                "a",
+                "2023-01-01",
+                &["2023-01-01", "Alice", "swing out"],
+                false,
+            ),
+            LibraryVideo::for_test("u", "", &["Alice"], false),
+        ];
+        let neighbors = video_neighbors(&videos);
+
+        let d = &neighbors[0];
+        assert_eq!(
+            d.by_date.previous.map(|video| video.video.as_str()),
+            Some("b")
+        );
+        assert_eq!(d.by_date.next, None);
+        assert_eq!(
+            d.by_tag
+                .iter()
+                .map(|(tag, neighbors)| (*tag, neighbors.previous.map(|v| v.video.as_str())))
+                .collect_vec(),
+            vec![("Alice", Some("a")), ("swing out", Some("b"))]
+        );
+        assert_eq!(names(&d.related), vec!["a", "b", "u"]);
+
+        assert_eq!(neighbors[1], VideoNeighbors::default());
+
+        let u = &neighbors[4];
+        assert_eq!(u.by_date, Neighbors::default());
+        assert_eq!(
+            u.by_tag[0].1.next.map(|video| video.video.as_str()),
+            Some("a")
+        );
+        assert_eq!(names(&u.related), vec!["a", "d"]);
+    }
+}
@@ -0,0 +1,48 @@
+use crate::build::date::Date;
+use crate::build::restrictions::{Restrictions, VideoStatus};
+use crate::tags_file::TagsFile;
+use crate::utils::maybe_read_string;
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::fs;
+
+const VIDEO_STATUSES_PATH: &str = "data/video_statuses.json";
+
+/// Decide how each video is published today, reporting the videos whose status changed since the
+/// previous build
+pub fn update_video_statuses(
+    all_tags: &TagsFile,
+    restrictions: &Restrictions,
+    today: Date,
+) -> anyhow::Result<BTreeMap<String, VideoStatus>> {
+    let previous: BTreeMap<String, VideoStatus> = match maybe_read_string(VIDEO_STATUSES_PATH)? {
+        None => BTreeMap::new(),
+        Some(data) => serde_json::from_str(&data)
+            .with_context(|| format!("failed to parse {}", VIDEO_STATUSES_PATH))?,
+    };
+
+    let mut statuses = BTreeMap::new();
+    for video in &all_tags.videos {
+        let status = restrictions.status(video, today);
+
+        if let Some(previous_status) = previous.get(&video.name) {
+            if previous_status != &status {
+                log::info!(
+                    "Video {} changed from {} to {}",
+                    video.name,
+                    previous_status,
+                    status
+                );
+            }
+        }
+
+        statuses.insert(video.name.clone(), status);
+    }
+
+    fs::write(
+        VIDEO_STATUSES_PATH,
+        serde_json::to_string_pretty(&statuses)?,
+    )?;
+
+    Ok(statuses)
+}
@@ -0,0 +1,108 @@
+use crate::utils::list_files_recursively;
+use anyhow::Context;
+use rust_embed::Embed;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// The directory whose files replace the embedded files of `web_src` with the same path, like
+/// `data/web/pages/about_page.html.hbs` or `data/web/static/css.css`. New files under `static/` are
+/// published too.
+pub const WEB_OVERRIDE_DIR: &str = "data/web";
+
+#[derive(Embed)]
+#[folder = "web_src"]
+struct Asset;
+
+/// The content of a file of `web_src`, read from the override directory when it is there
+pub fn asset_binary_data(name: &str) -> anyhow::Result<Vec<u8>> {
+    read_asset(Path::new(WEB_OVERRIDE_DIR), name)
+}
+
+fn read_asset(override_dir: &Path, name: &str) -> anyhow::Result<Vec<u8>> {
+    let path = override_dir.join(name);
+    match fs::read(&path) {
+        Ok(data) => return Ok(data),
+        Err(error) if error.kind() == ErrorKind::NotFound => {}
+        Err(error) => {
+            return Err(
+                anyhow::Error::from(error).context(format!("failed to read {}", path.display()))
+            )
+        }
+    }
+
+    let file = Asset::get(name).with_context(|| format!("missing static file {}", name))?;
+    Ok(file.data.into_owned())
+}
+
+pub fn asset_data(name: &str) -> anyhow::Result<String> {
+    let data = String::from_utf8(asset_binary_data(name)?)?;
+    Ok(data)
+}
+
+/// The names of the files under `static/`, embedded or from the override directory
+pub fn static_asset_names() -> anyhow::Result<BTreeSet<String>> {
+    list_static_assets(Path::new(WEB_OVERRIDE_DIR))
+}
+
+fn list_static_assets(override_dir: &Path) -> anyhow::Result<BTreeSet<String>> {
+    let mut names: BTreeSet<_> = Asset::iter()
+        .filter(|item| item.starts_with("static/"))
+        .map(|item| item.into_owned())
+        .collect();
+
+    let static_dir = override_dir.join("static");
+    if fs::exists(&static_dir)? {
+        for file in list_files_recursively(&static_dir)? {
+            let name = file.strip_prefix(override_dir)?;
+            names.insert(name.to_str().context("invalid file name")?.to_string());
+        }
+    }
+
+    Ok(names)
+}
+
+/// The names of the embedded files used by the build, which can be overridden
+pub fn embedded_asset_names() -> Vec<String> {
+    Asset::iter()
+        .filter(|item| !item.starts_with("debug/"))
+        .map(|item| item.into_owned())
+        .collect()
+}
+
+/// The content of an embedded file, ignoring the override directory
+pub fn embedded_asset_data(name: &str) -> anyhow::Result<Vec<u8>> {
+    let file = Asset::get(name).with_context(|| format!("missing static file {}", name))?;
+    Ok(file.data.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_override_dir() {
+        let dir = std::env::temp_dir().join(format!("lindy-hop-web-{}", std::process::id()));
+        fs::create_dir_all(dir.join("static/extra")).unwrap();
+        fs::write(dir.join("static/css.css"), "body {}").unwrap();
+        fs::write(dir.join("static/extra/new.txt"), "new").unwrap();
+
+        assert_eq!(read_asset(&dir, "static/css.css").unwrap(), b"body {}");
+        assert_eq!(read_asset(&dir, "static/extra/new.txt").unwrap(), b"new");
+        // The files that are not overridden come from `web_src`
+        assert_eq!(
+            read_asset(&dir, "static/js.mjs").unwrap(),
+            embedded_asset_data("static/js.mjs").unwrap()
+        );
+        assert!(read_asset(&dir, "static/missing.txt").is_err());
+
+        let names = list_static_assets(&dir).unwrap();
+        assert!(names.contains("static/css.css"));
+        assert!(names.contains("static/js.mjs"));
+        assert!(names.contains("static/extra/new.txt"));
+        assert!(!names.iter().any(|name| name.starts_with("pages/")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -4,8 +4,10 @@ use serde::Deserialize;
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub file_access_iterations: u32,
-    pub file_access_salt: String,
+    pub page_size: usize,
     pub public_url: String,
+    pub restriction_expiry_warning_days: u32,
+    pub secrets_path: String,
     pub thumbnail_height: u32,
     pub thumbnail_hex_chars_prefix: usize,
 }
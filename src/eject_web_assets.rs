@@ -0,0 +1,43 @@
+use crate::build::{embedded_asset_data, embedded_asset_names, WEB_OVERRIDE_DIR};
+use anyhow::ensure;
+use std::fs;
+use std::path::Path;
+
+/// Copy the embedded templates, messages and static files into the override directory, to
+/// customise them. Only the files whose path starts with one of `prefixes` are copied, or all of
+/// them if there is none. Existing files are kept unless `force` is set.
+pub fn eject_web_assets(prefixes: &[String], force: bool) -> anyhow::Result<()> {
+    let names = embedded_asset_names()
+        .into_iter()
+        .filter(|name| {
+            prefixes.is_empty() || prefixes.iter().any(|prefix| name.starts_with(prefix))
+        })
+        .collect::<Vec<_>>();
+    ensure!(!names.is_empty(), "no embedded file matches {:?}", prefixes);
+
+    let mut written = 0;
+    for name in names {
+        let path = Path::new(WEB_OVERRIDE_DIR).join(&name);
+        if !force && fs::exists(&path)? {
+            log::warn!(
+                "Keep existing {}, use --force to replace it",
+                path.display()
+            );
+            continue;
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, embedded_asset_data(&name)?)?;
+        written += 1;
+    }
+
+    log::info!(
+        "Wrote {} files to {}. Delete the ones you do not change, so that they keep following the defaults",
+        written,
+        WEB_OVERRIDE_DIR
+    );
+
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use crate::build::{read_all_tags, read_restrictions, Date, Restrictions, RuleState, VideoStatus};
+use crate::tags_file::TagsFile;
+use clap::ValueEnum;
+use itertools::Itertools;
+use serde::Serialize;
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    date: Date,
+    rules: Vec<RuleReport<'a>>,
+    /// The rules that match no video
+    unmatched_rules: Vec<&'a str>,
+    /// The public videos with a literal tag that selects the videos of some rule, which are likely
+    /// mistakes
+    suspicious_public_videos: Vec<SuspiciousVideo<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct RuleReport<'a> {
+    name: &'a str,
+    state: RuleState,
+    videos: Vec<MatchedVideo<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct MatchedVideo<'a> {
+    video: &'a str,
+    /// The other rules that match the video, which grant access to it too
+    also_matched_by: Vec<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct SuspiciousVideo<'a> {
+    video: &'a str,
+    /// The tags of the video that select the videos of some rule
+    tags: Vec<&'a str>,
+}
+
+/// Print what each rule of `data/restrictions.json` protects on the current date
+pub fn explain_restrictions(format: OutputFormat) -> anyhow::Result<()> {
+    let all_tags = read_all_tags()?;
+    let restrictions = read_restrictions()?;
+    let report = build_report(&all_tags, &restrictions, Date::today()?);
+
+    match format {
+        OutputFormat::Table => print_table(&report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    Ok(())
+}
+
+fn build_report<'a>(
+    all_tags: &'a TagsFile,
+    restrictions: &'a Restrictions,
+    today: Date,
+) -> Report<'a> {
+    let mut report = Report {
+        date: today,
+        rules: Vec::with_capacity(restrictions.rules.len()),
+        unmatched_rules: Vec::new(),
+        suspicious_public_videos: Vec::new(),
+    };
+
+    for rule in &restrictions.rules {
+        let videos = all_tags
+            .videos
+            .iter()
+            .filter(|video| rule.matches(video))
+            .map(|video| MatchedVideo {
+                video: &video.name,
+                also_matched_by: restrictions
+                    .rules
+                    .iter()
+                    .filter(|other| other.name != rule.name && other.matches(video))
+                    .map(|other| other.name.as_str())
+                    .collect(),
+            })
+            .collect_vec();
+
+        if videos.is_empty() {
+            report.unmatched_rules.push(&rule.name);
+        }
+        report.rules.push(RuleReport {
+            name: &rule.name,
+            state: rule.state(today),
+            videos,
+        });
+    }
+
+    for video in &all_tags.videos {
+        if restrictions.status(video, today) != VideoStatus::Public {
+            continue;
+        }
+
+        let tags = video
+            .tags
+            .iter()
+            .filter(|tag| restrictions.rules.iter().any(|rule| rule.mentions_tag(tag)))
+            .map(|tag| tag.as_str())
+            .collect_vec();
+        if !tags.is_empty() {
+            report.suspicious_public_videos.push(SuspiciousVideo {
+                video: &video.name,
+                tags,
+            });
+        }
+    }
+
+    report
+}
+
+fn print_table(report: &Report) {
+    let mut rows = vec![[
+        "RULE".to_string(),
+        "STATE".to_string(),
+        "VIDEO".to_string(),
+        "ALSO MATCHED BY".to_string(),
+    ]];
+    for rule in &report.rules {
+        if rule.videos.is_empty() {
+            rows.push([
+                rule.name.to_string(),
+                rule.state.to_string(),
+                "-".to_string(),
+                String::new(),
+            ]);
+        }
+
+        for video in &rule.videos {
+            rows.push([
+                rule.name.to_string(),
+                rule.state.to_string(),
+                video.video.to_string(),
+                video.also_matched_by.join(", "),
+            ]);
+        }
+    }
+
+    let widths: Vec<_> = (0..4)
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    println!("Restrictions on {}", report.date);
+    println!();
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+
+    if !report.unmatched_rules.is_empty() {
+        println!();
+        println!(
+            "Rules that match no video: {}",
+            report.unmatched_rules.join(", ")
+        );
+    }
+
+    if !report.suspicious_public_videos.is_empty() {
+        println!();
+        println!("Public videos with tags selected by the rules:");
+        for video in &report.suspicious_public_videos {
+            println!("  {}  {}", video.video, video.tags.join(", "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_report() {
+        let all_tags: TagsFile =
+            "[a.mp4]\ncamp\nAlice\n\n[b.mp4]\ncamp\n\n[c.mp4]\nBob\n\n[d.mp4]\nAlice\n"
+                .parse()
+                .unwrap();
+        let restrictions: Restrictions = serde_json::from_str(
+            r#"{"rules": [
+                {"name": "camp", "with_tags": ["camp"], "password": "1234"},
+                {"name": "alice", "expression": "Alice AND camp", "password": "1234"},
+                {"name": "nobody", "with_tags": ["Carol"], "password": "1234"}
+            ]}"#,
+        )
+        .unwrap();
+        let report = build_report(&all_tags, &restrictions, "2024-01-01".parse().unwrap());
+
+        let videos = |index: usize| {
+            report.rules[index]
+                .videos
+                .iter()
+                .map(|video| (video.video, video.also_matched_by.clone()))
+                .collect_vec()
+        };
+        assert_eq!(videos(0), vec![("a.mp4", vec!["alice"]), ("b.mp4", vec![])]);
+        assert_eq!(videos(1), vec![("a.mp4", vec!["camp"])]);
+        assert_eq!(videos(2), vec![]);
+        assert_eq!(report.unmatched_rules, vec!["nobody"]);
+        // d.mp4 is public but has the tag Alice, selected by name by the rule alice
+        assert_eq!(
+            report
+                .suspicious_public_videos
+                .iter()
+                .map(|video| (video.video, video.tags.clone()))
+                .collect_vec(),
+            vec![("d.mp4", vec!["Alice"])]
+        );
+    }
+}
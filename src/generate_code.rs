@@ -0,0 +1,17 @@
+use crate::build::{generate_code, password_entropy_bits};
+use crate::config::Config;
+
+/// Print a new memorable code for a rule, with the cost of brute-forcing it
+pub fn print_generated_code(config: &Config, words: usize) -> anyhow::Result<()> {
+    let code = generate_code(words);
+    let strength = password_entropy_bits(&code) + f64::from(config.file_access_iterations).log2();
+
+    println!("{}", code);
+    log::info!(
+        "This code takes {:.0} bits of work to brute-force with {} PBKDF2 iterations",
+        strength,
+        config.file_access_iterations
+    );
+
+    Ok(())
+}
@@ -1,21 +1,34 @@
 use crate::build::build;
 use crate::config::Config;
 use crate::copy_new_videos::copy_new_videos;
+use crate::eject_web_assets::eject_web_assets;
+use crate::explain_restrictions::{explain_restrictions, OutputFormat};
+use crate::generate_code::print_generated_code;
 use crate::list_mtp_mounts::list_mtp_mounts;
 use crate::prepare_new_videos_for_tagging::prepare_new_videos_for_tagging;
 use crate::re_encode_videos::re_encode_videos;
-use clap::Parser;
+use crate::set_rule_password::set_rule_password;
+use crate::share_rule::share_rule;
+use crate::watch::watch;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use std::time::Duration;
 
 mod build;
 mod config;
 mod copy_new_videos;
+mod eject_web_assets;
+mod explain_restrictions;
+mod generate_code;
 mod hash_file;
 mod list_mtp_mounts;
 mod prepare_new_videos_for_tagging;
 mod re_encode_videos;
+mod set_rule_password;
+mod share_rule;
 mod tags_file;
 mod utils;
+mod watch;
 
 #[derive(Parser)]
 enum Cli {
@@ -33,6 +46,25 @@ enum Cli {
     },
     /// Ingest all new videos and tags and produce the final artifacts
     Build,
+    /// Build, then watch the tags, restrictions and tagging parts to rebuild incrementally
+    Watch {
+        /// How long to wait for successive edits to settle before rebuilding
+        #[clap(long, default_value_t = 500)]
+        debounce_ms: u64,
+    },
+    /// Copy the embedded templates, messages and static files into `data/web` to customise them.
+    /// The build reads the files of `data/web` first, falling back to the embedded ones
+    EjectWebAssets {
+        /// Only copy the files whose path starts with these, like `pages/about_page.html.hbs` or
+        /// `static/`
+        prefixes: Vec<String>,
+        /// Replace the files that already exist
+        #[clap(long)]
+        force: bool,
+    },
+    /// Inspect the rules in `data/restrictions.json` and manage their passwords
+    #[clap(subcommand)]
+    Restrictions(RestrictionsCommand),
     /// Re-encode large videos to reduce storage
     ReEncodeVideos {
         /// Maximum number of pixels on the smallest dimension.
@@ -53,6 +85,37 @@ enum Cli {
     },
 }
 
+#[derive(Subcommand)]
+enum RestrictionsCommand {
+    /// Print the videos matched by each rule, the rules that match nothing and the public videos
+    /// that carry tags used by the rules
+    Explain {
+        #[clap(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Ask for the password of a rule and store it in the secrets file `SECRETS_PATH`, out of
+    /// `data/restrictions.json`
+    SetPassword {
+        /// The name of the rule
+        rule: String,
+    },
+    /// Generate a memorable code of random French words, to use as the password of a new rule
+    GenerateCode {
+        /// The number of words. Each one adds about 10 bits of strength
+        #[clap(long, default_value_t = 4)]
+        words: usize,
+    },
+    /// Print a link that unlocks the videos of a rule, with the code in its fragment, and write
+    /// its QR code as SVG and PNG
+    Share {
+        /// The name of the rule
+        rule: String,
+        /// Where to write the QR codes. Keep them out of `build`, since they carry the code
+        #[clap(long, default_value = ".")]
+        output_dir: PathBuf,
+    },
+}
+
 fn main() -> anyhow::Result<()> {
     let _ = dotenvy::from_path(".env");
     dotenvy::from_path("default.env")?;
@@ -67,6 +130,18 @@ fn main() -> anyhow::Result<()> {
         Cli::CopyNewVideos { mount } => copy_new_videos(mount),
         Cli::PrepareNewVideosForTagging { part_size } => prepare_new_videos_for_tagging(part_size),
         Cli::Build => build(&config),
+        Cli::Watch { debounce_ms } => watch(&config, Duration::from_millis(debounce_ms)),
+        Cli::EjectWebAssets { prefixes, force } => eject_web_assets(&prefixes, force),
+        Cli::Restrictions(RestrictionsCommand::Explain { format }) => explain_restrictions(format),
+        Cli::Restrictions(RestrictionsCommand::SetPassword { rule }) => {
+            set_rule_password(&config, &rule)
+        }
+        Cli::Restrictions(RestrictionsCommand::GenerateCode { words }) => {
+            print_generated_code(&config, words)
+        }
+        Cli::Restrictions(RestrictionsCommand::Share { rule, output_dir }) => {
+            share_rule(&config, &rule, &output_dir)
+        }
         Cli::ReEncodeVideos {
             max_lines,
             max_fps,
@@ -0,0 +1,83 @@
+use crate::build::{read_restrictions, SecretsFile, RESTRICTIONS_PATH};
+use crate::config::Config;
+use anyhow::{ensure, Context};
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+/// Ask for the password of a rule and store it in the secrets file
+pub fn set_rule_password(config: &Config, rule_name: &str) -> anyhow::Result<()> {
+    let restrictions = read_restrictions()?;
+    let rule = restrictions
+        .rules
+        .iter()
+        .find(|rule| rule.name == rule_name)
+        .with_context(|| format!("no rule named {:?} in {}", rule_name, RESTRICTIONS_PATH))?;
+
+    eprint!("Password for the rule {}: ", rule.name);
+    io::stderr().flush()?;
+    let password = read_hidden_line()?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    ensure!(!password.is_empty(), "empty password");
+
+    let path = Path::new(&config.secrets_path);
+    let mut secrets = SecretsFile::read(path)?;
+    secrets.set(rule.secret_name(), password);
+    secrets.write(path)?;
+    log::info!(
+        "Stored the secret {} in {}",
+        rule.secret_name(),
+        path.display()
+    );
+
+    if rule.password.is_some() {
+        log::warn!(
+            "Rule {} still has a password in {}, which takes precedence: remove it",
+            rule.name,
+            RESTRICTIONS_PATH
+        );
+    }
+
+    Ok(())
+}
+
+/// Read a line from the standard input, without echoing it when it is a terminal
+#[cfg(unix)]
+fn read_hidden_line() -> anyhow::Result<String> {
+    use std::io::IsTerminal;
+    use std::os::fd::AsRawFd;
+
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return read_line();
+    }
+
+    let fd = stdin.as_raw_fd();
+    // SAFETY: `termios` is a plain C struct, that `tcgetattr` fills before it is used
+    let mut original: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    let mut hidden = original;
+    // Still echo the final new line, so that the next output starts on its own line
+    hidden.c_lflag &= !libc::ECHO;
+    hidden.c_lflag |= libc::ECHONL;
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &hidden) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    let line = read_line();
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    line
+}
+
+#[cfg(not(unix))]
+fn read_hidden_line() -> anyhow::Result<String> {
+    read_line()
+}
+
+fn read_line() -> anyhow::Result<String> {
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line)
+}
@@ -0,0 +1,90 @@
+use crate::build::{
+    clean_name, collection_page, read_restrictions, resolve_password, RESTRICTIONS_PATH,
+};
+use crate::config::Config;
+use anyhow::Context;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use qrcode::render::svg;
+use qrcode::{Color, QrCode};
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Size of each QR code module in the PNG, in pixels
+const PNG_MODULE_SIZE: usize = 10;
+/// Number of blank modules around the QR code, as required by the standard
+const QUIET_ZONE: usize = 4;
+
+/// Print the unlock link of the collection page of a rule and write its QR code, as SVG and PNG,
+/// into `output_dir`. The code is carried by the URL fragment, which browsers never send to the
+/// server.
+pub fn share_rule(config: &Config, rule_name: &str, output_dir: &Path) -> anyhow::Result<()> {
+    let restrictions = read_restrictions()?;
+    let rule = restrictions
+        .rules
+        .iter()
+        .find(|rule| rule.name == rule_name)
+        .with_context(|| format!("no rule named {:?} in {}", rule_name, RESTRICTIONS_PATH))?;
+    let password = resolve_password(config, rule)?;
+
+    let url = format!(
+        "{}/{}#unlock={}&code={}",
+        config.public_url,
+        collection_page(&rule.name),
+        utf8_percent_encode(&rule.name, NON_ALPHANUMERIC),
+        utf8_percent_encode(&password, NON_ALPHANUMERIC)
+    );
+    println!("{}", url);
+
+    let code = QrCode::new(url.as_bytes()).context("failed to create QR code")?;
+    fs::create_dir_all(output_dir)?;
+    let stem = output_dir.join(clean_name(&rule.name));
+
+    let svg_path = stem.with_extension("svg");
+    let svg = code
+        .render::<svg::Color>()
+        .min_dimensions(400, 400)
+        .quiet_zone(true)
+        .build();
+    fs::write(&svg_path, svg)?;
+    log::info!("Wrote {}", svg_path.display());
+
+    let png_path = stem.with_extension("png");
+    write_png(&code, &png_path)?;
+    log::info!("Wrote {}", png_path.display());
+
+    Ok(())
+}
+
+fn write_png(code: &QrCode, path: &Path) -> anyhow::Result<()> {
+    let modules = code.width();
+    let colors = code.to_colors();
+    let size = (modules + 2 * QUIET_ZONE) * PNG_MODULE_SIZE;
+
+    let mut pixels = vec![u8::MAX; size * size];
+    for (index, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+
+        let x = (index % modules + QUIET_ZONE) * PNG_MODULE_SIZE;
+        let y = (index / modules + QUIET_ZONE) * PNG_MODULE_SIZE;
+        for row in y..y + PNG_MODULE_SIZE {
+            pixels[row * size + x..row * size + x + PNG_MODULE_SIZE].fill(0);
+        }
+    }
+
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        size as u32,
+        size as u32,
+    );
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+
+    Ok(())
+}
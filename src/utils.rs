@@ -40,3 +40,11 @@ pub fn list_dirs(path: impl AsRef<Path>) -> anyhow::Result<Vec<PathBuf>> {
 
     Ok(dirs)
 }
+
+pub fn list_files_recursively(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = list_files(dir)?;
+    for subdir in list_dirs(dir)? {
+        files.extend(list_files_recursively(&subdir)?);
+    }
+    Ok(files)
+}
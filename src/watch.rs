@@ -0,0 +1,323 @@
+use crate::build::{
+    ingest_tags, read_all_tags, render_pages, update_library, video_neighbors, Library,
+    PageSelection, ALL_TAGS_PATH, PLAYLISTS_DIR, RESTRICTIONS_PATH, TAGGING_IN_PROGRESS_DIR,
+    WEB_OVERRIDE_DIR,
+};
+use crate::config::Config;
+use crate::tags_file::TagsFile;
+use crate::utils::{list_dirs, maybe_read_string};
+use anyhow::Context;
+use notify::{RecursiveMode, Watcher};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Watch the tags, restrictions and tagging parts, rebuilding only what is affected by each change.
+/// Changes to the template overrides and to the playlists, if their directories exist when starting,
+/// render all the pages.
+pub fn watch(config: &Config, debounce: Duration) -> anyhow::Result<()> {
+    fs::create_dir_all(TAGGING_IN_PROGRESS_DIR)?;
+    let data_dir = Path::new("data").canonicalize()?;
+    let tagging_dir = Path::new(TAGGING_IN_PROGRESS_DIR).canonicalize()?;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&data_dir, RecursiveMode::NonRecursive)?;
+    watcher.watch(&tagging_dir, RecursiveMode::Recursive)?;
+    for dir in [WEB_OVERRIDE_DIR, PLAYLISTS_DIR] {
+        if fs::exists(dir)? {
+            watcher.watch(&Path::new(dir).canonicalize()?, RecursiveMode::Recursive)?;
+        }
+    }
+
+    log::info!("Initial build");
+    let mut state = WatchState::default();
+    if let Err(error) = state.build_all(config) {
+        log::error!("Failed the initial build: {:?}", error);
+    }
+
+    log::info!("Watching for changes");
+    loop {
+        let mut changes = Changes::default();
+        let event = receiver.recv().context("watcher disconnected")?;
+        changes.add(&data_dir, &tagging_dir, event);
+
+        // Wait for a quiet period, so that rapid successive edits produce a single rebuild
+        loop {
+            match receiver.recv_timeout(debounce) {
+                Ok(event) => changes.add(&data_dir, &tagging_dir, event),
+                Err(mpsc::RecvTimeoutError::Timeout) => break,
+                Err(mpsc::RecvTimeoutError::Disconnected) => anyhow::bail!("watcher disconnected"),
+            }
+        }
+
+        if changes.is_empty() {
+            continue;
+        }
+
+        if let Err(error) = state.apply(config, &changes) {
+            log::error!("Failed to apply changes: {:?}", error);
+        }
+    }
+}
+
+#[derive(Default)]
+struct WatchState {
+    /// Whether the last full build failed, or did not run yet, so that the next change builds
+    /// everything again instead of only the touched pages
+    needs_full_build: bool,
+    all_tags: TagsFile,
+    /// The last content of `data/all_tags.txt` read or written by the watcher
+    all_tags_content: String,
+    library: Library,
+}
+
+#[derive(Debug, Default)]
+struct Changes {
+    all_tags: bool,
+    restrictions: bool,
+    parts: BTreeSet<PathBuf>,
+    /// A template, message or static file of the override directory
+    web: bool,
+    playlists: bool,
+}
+
+impl WatchState {
+    /// Read the tags, ingest the parts and render all the pages
+    fn build_all(&mut self, config: &Config) -> anyhow::Result<()> {
+        self.needs_full_build = true;
+
+        let all_tags_content = maybe_read_string(ALL_TAGS_PATH)?.unwrap_or_default();
+        self.all_tags = all_tags_content.parse()?;
+        self.all_tags_content = all_tags_content;
+
+        let parts = list_dirs(TAGGING_IN_PROGRESS_DIR)?;
+        self.ingest_parts(parts.iter())?;
+
+        self.library = update_library(config, &self.all_tags)?;
+        render_pages(config, &self.library, &PageSelection::All)?;
+
+        self.needs_full_build = false;
+        Ok(())
+    }
+
+    fn apply(&mut self, config: &Config, changes: &Changes) -> anyhow::Result<()> {
+        if self.needs_full_build {
+            log::info!("Retry the full build");
+            return self.build_all(config);
+        }
+
+        let mut library_changed = changes.restrictions;
+
+        if changes.all_tags {
+            let content = maybe_read_string(ALL_TAGS_PATH)?.unwrap_or_default();
+            if content != self.all_tags_content {
+                log::info!("Detected changes in {}", ALL_TAGS_PATH);
+                self.all_tags = read_all_tags()?;
+                self.all_tags_content = content;
+                library_changed = true;
+            }
+        }
+
+        if self.ingest_parts(changes.parts.iter())? {
+            library_changed = true;
+        }
+
+        if changes.renders_all() && !library_changed {
+            log::info!(
+                "Detected changes in {} or {}",
+                WEB_OVERRIDE_DIR,
+                PLAYLISTS_DIR
+            );
+            return render_pages(config, &self.library, &PageSelection::All);
+        }
+
+        if !library_changed {
+            return Ok(());
+        }
+
+        let library = update_library(config, &self.all_tags)?;
+        let selection = if changes.renders_all() {
+            PageSelection::All
+        } else {
+            touched_pages(&self.library, &library)
+        };
+        log::info!("Will render the touched pages: {:?}", selection);
+        render_pages(config, &library, &selection)?;
+        self.library = library;
+
+        Ok(())
+    }
+
+    /// Ingest the given parts, persisting the tags if anything was ingested. A part that fails is
+    /// reported and skipped, after the videos ingested before the failure.
+    fn ingest_parts<'a>(
+        &mut self,
+        parts: impl Iterator<Item = &'a PathBuf>,
+    ) -> anyhow::Result<bool> {
+        let before = self.all_tags.to_string();
+        let mut ingested = 0;
+        for part in parts {
+            if !fs::exists(part.join("tags.txt"))? {
+                continue;
+            }
+
+            match ingest_tags(&mut self.all_tags, part) {
+                Ok(part_ingested) => ingested += part_ingested,
+                Err(error) => log::error!("Failed to ingest {}: {:?}", part.display(), error),
+            }
+        }
+
+        // In all cases, persist the new tags: the videos ingested by a part that failed later on
+        // have already been moved to `data/videos`
+        let content = self.all_tags.to_string();
+        if content == before {
+            return Ok(false);
+        }
+
+        if ingested > 0 {
+            log::info!("Ingested {} new videos", ingested);
+        }
+        self.all_tags_content = content;
+        fs::write(ALL_TAGS_PATH, &self.all_tags_content)?;
+        Ok(true)
+    }
+}
+
+impl Changes {
+    fn add(&mut self, data_dir: &Path, tagging_dir: &Path, event: notify::Result<notify::Event>) {
+        let event = match event {
+            Ok(event) => event,
+            Err(error) => {
+                log::warn!("Watch error: {}", error);
+                return;
+            }
+        };
+
+        if event.kind.is_access() {
+            return;
+        }
+
+        for path in event.paths {
+            if let Ok(relative) = path.strip_prefix(tagging_dir) {
+                if let Some(part) = relative.components().next() {
+                    self.parts
+                        .insert(Path::new(TAGGING_IN_PROGRESS_DIR).join(part));
+                }
+            } else if path == data_dir.join(file_name(ALL_TAGS_PATH)) {
+                self.all_tags = true;
+            } else if path == data_dir.join(file_name(RESTRICTIONS_PATH)) {
+                self.restrictions = true;
+            } else if path.starts_with(data_dir.join(file_name(WEB_OVERRIDE_DIR))) {
+                self.web = true;
+            } else if path.starts_with(data_dir.join(file_name(PLAYLISTS_DIR))) {
+                self.playlists = true;
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.all_tags && !self.restrictions && self.parts.is_empty() && !self.renders_all()
+    }
+
+    /// Whether the changes affect all the pages, and not only those of the touched videos and tags
+    fn renders_all(&self) -> bool {
+        self.web || self.playlists
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit_once('/').map_or(path, |(_, name)| name)
+}
+
+/// Detect which video and tag pages are affected by the differences between two libraries
+fn touched_pages(old: &Library, new: &Library) -> PageSelection {
+    let old_videos: BTreeMap<_, _> = old.videos.iter().map(|v| (v.video.as_str(), v)).collect();
+    let new_videos: BTreeMap<_, _> = new.videos.iter().map(|v| (v.video.as_str(), v)).collect();
+
+    let mut videos = BTreeSet::new();
+    let mut tags = BTreeSet::new();
+    for name in old_videos.keys().chain(new_videos.keys()) {
+        let old_video = old_videos.get(name);
+        let new_video = new_videos.get(name);
+
+        if old_video != new_video {
+            videos.insert(name.to_string());
+            for video in old_video.into_iter().chain(new_video) {
+                tags.extend(video.tags.iter().cloned());
+            }
+        }
+    }
+
+    // The pages of the other videos link to their neighbors, which may have changed too
+    let old_neighbors: BTreeMap<_, _> = old
+        .videos
+        .iter()
+        .map(|video| video.video.as_str())
+        .zip(video_neighbors(&old.videos))
+        .collect();
+    for (video, neighbors) in new.videos.iter().zip(video_neighbors(&new.videos)) {
+        if old_neighbors.get(video.video.as_str()) != Some(&neighbors) {
+            videos.insert(video.video.clone());
+        }
+    }
+
+    PageSelection::Only { videos, tags }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::LibraryVideo;
+
+    fn library(videos: Vec<LibraryVideo>) -> Library {
+        Library {
+            videos,
+            collections: Vec::new(),
+        }
+    }
+
+    fn set(values: &[&str]) -> BTreeSet<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_touched_pages() {
+        let videos = || {
+            vec![
+                LibraryVideo::for_test("e", "", &["Dave"], false),
+                LibraryVideo::for_test("d", "2024-04-01", &["2024-04-01", "Carol"], false),
+                LibraryVideo::for_test("c", "2024-03-01", &["2024-03-01", "Bob"], false),
+                LibraryVideo::for_test("b", "2024-02-01", &["2024-02-01", "Alice", "Bob"], false),
+                LibraryVideo::for_test("a", "2024-01-01", &["2024-01-01", "Alice"], false),
+            ]
+        };
+        let old = library(videos());
+        let mut new = library(videos());
+        // The tags of b change
+        new.videos[3].tags = ["2024-02-01", "Alice", "Charlie"]
+            .map(String::from)
+            .to_vec();
+        // A restriction rule now hides d
+        new.videos[1].hidden = true;
+
+        let PageSelection::Only { videos, tags } = touched_pages(&old, &new) else {
+            panic!("expected a partial selection");
+        };
+        // a and c only change through their neighbors, and e is not related to any change
+        assert_eq!(videos, set(&["a", "b", "c", "d"]));
+        assert_eq!(
+            tags,
+            set(&[
+                "2024-02-01",
+                "2024-04-01",
+                "Alice",
+                "Bob",
+                "Carol",
+                "Charlie"
+            ])
+        );
+    }
+}
@@ -0,0 +1,252 @@
+// Service worker that decrypts the encrypted videos while they are played. The video element
+// requests `<site>/decrypted/<file name>` by ranges, and each range is answered by fetching and
+// decrypting only the chunks that cover it, so that the playback starts right away and seeking
+// does not need the whole file. The key of each file is asked to the page that plays it, in
+// `js.mjs`, so that it never appears in a URL.
+//
+// It is published at the root of the site, since a service worker only controls the pages under
+// its own directory.
+
+// Must match the format produced by `encrypt::encrypt_stream` in the Rust code
+const FILE_NONCE_SIZE = 12
+const FILE_CHUNK_SIZE = 1024 * 1024
+const FILE_TAG_SIZE = 16
+const FILE_SEALED_CHUNK_SIZE = FILE_CHUNK_SIZE + FILE_TAG_SIZE
+
+const DECRYPTED_DIR = 'decrypted/'
+
+/**
+ * @typedef {{url: string, key: string}} SourceRequest
+ * @typedef {{url: string, key: CryptoKey, baseNonce: Uint8Array, encryptedSize: number,
+ *   chunks: number, size: number}} Source
+ */
+
+/**
+ * The opened sources, by URL and key
+ * @type {Map<string, Promise<Source>>}
+ */
+const sources = new Map()
+
+self.addEventListener('install', () => {
+  self.skipWaiting()
+})
+
+self.addEventListener('activate', event => {
+  event.waitUntil(self.clients.claim())
+})
+
+self.addEventListener('fetch', event => {
+  const prefix = new URL(DECRYPTED_DIR, self.registration.scope).href
+  if (!event.request.url.startsWith(prefix)) {
+    return
+  }
+
+  const name = event.request.url.slice(prefix.length)
+  event.respondWith(respondDecrypted(event, name).catch(error => {
+    console.error(error)
+    return new Response(null, {status: 500})
+  }))
+})
+
+/**
+ * @param {FetchEvent} event
+ * @param {string} name
+ * @returns {Promise<Response>}
+ */
+async function respondDecrypted(event, name) {
+  const request = await requestSource(event.clientId, name)
+  const source = await openSource(request)
+
+  const rangeHeader = event.request.headers.get('Range')
+  const range = rangeHeader === null ? [0, source.size - 1] : parseRange(rangeHeader, source.size)
+  if (range === null) {
+    return new Response(null, {status: 416, headers: {'Content-Range': `bytes */${source.size}`}})
+  }
+
+  const [start, end] = range
+  const headers = {
+    'Accept-Ranges': 'bytes',
+    'Content-Length': String(end - start + 1),
+    'Content-Type': name.includes('.mp4') ? 'video/mp4' : 'application/octet-stream',
+  }
+  if (rangeHeader === null) {
+    return new Response(decryptedStream(source, start, end), {status: 200, headers})
+  }
+
+  headers['Content-Range'] = `bytes ${start}-${end}/${source.size}`
+  return new Response(decryptedStream(source, start, end), {status: 206, headers})
+}
+
+/**
+ * Ask the pages for the encrypted file and key of a decrypted video
+ * @param {string} clientId
+ * @param {string} name
+ * @returns {Promise<SourceRequest>}
+ */
+async function requestSource(clientId, name) {
+  const client = clientId ? await self.clients.get(clientId) : undefined
+  const clients = client ? [client] : await self.clients.matchAll({type: 'window'})
+
+  for (const client of clients) {
+    const request = await new Promise(resolve => {
+      const channel = new MessageChannel()
+      channel.port1.onmessage = event => resolve(event.data)
+      client.postMessage({type: 'decryption-source', name}, [channel.port2])
+    })
+    if (request !== null) {
+      return request
+    }
+  }
+
+  throw new Error(`no page knows the key of ${name}`)
+}
+
+/**
+ * @param {SourceRequest} request
+ * @returns {Promise<Source>}
+ */
+function openSource(request) {
+  const cacheKey = `${request.url} ${request.key}`
+  let source = sources.get(cacheKey)
+  if (source === undefined) {
+    source = readSource(request)
+    sources.set(cacheKey, source)
+    source.catch(() => sources.delete(cacheKey))
+  }
+  return source
+}
+
+/**
+ * Read the base nonce and the size of an encrypted file
+ * @param {SourceRequest} request
+ * @returns {Promise<Source>}
+ */
+async function readSource(request) {
+  const key = await crypto.subtle.importKey(
+    'raw',
+    hexToBytes(request.key),
+    'AES-GCM',
+    false,
+    ['decrypt'],
+  )
+
+  const response = await fetchRange(request.url, 0, FILE_NONCE_SIZE - 1)
+  const baseNonce = new Uint8Array(await response.arrayBuffer())
+  const encryptedSize = Number(response.headers.get('Content-Range').split('/')[1])
+  if (baseNonce.length !== FILE_NONCE_SIZE || !(encryptedSize >= FILE_NONCE_SIZE + FILE_TAG_SIZE)) {
+    throw new Error(`invalid encrypted file ${request.url}`)
+  }
+
+  const chunks = Math.max(1, Math.ceil((encryptedSize - FILE_NONCE_SIZE) / FILE_SEALED_CHUNK_SIZE))
+  return {
+    url: request.url,
+    key,
+    baseNonce,
+    encryptedSize,
+    chunks,
+    size: encryptedSize - FILE_NONCE_SIZE - chunks * FILE_TAG_SIZE,
+  }
+}
+
+/**
+ * Parse a single range of the `Range` header, or return `null` if it cannot be satisfied
+ * @param {string} header
+ * @param {number} size
+ * @returns {[number, number] | null}
+ */
+function parseRange(header, size) {
+  const match = /^bytes=(\d*)-(\d*)$/.exec(header.trim())
+  if (match === null || (match[1] === '' && match[2] === '')) {
+    return null
+  }
+
+  let start, end
+  if (match[1] === '') {
+    // The last bytes
+    start = Math.max(0, size - Number(match[2]))
+    end = size - 1
+  } else {
+    start = Number(match[1])
+    end = match[2] === '' ? size - 1 : Math.min(Number(match[2]), size - 1)
+  }
+
+  return start <= end ? [start, end] : null
+}
+
+/**
+ * The decrypted bytes from `start` to `end` included. Each chunk is only fetched when the video
+ * element reads that far, and nothing more is fetched once it stops reading.
+ * @param {Source} source
+ * @param {number} start
+ * @param {number} end
+ * @returns {ReadableStream<Uint8Array>}
+ */
+function decryptedStream(source, start, end) {
+  let index = Math.floor(start / FILE_CHUNK_SIZE)
+  const lastIndex = Math.floor(end / FILE_CHUNK_SIZE)
+
+  return new ReadableStream({
+    async pull(controller) {
+      const chunk = await decryptChunk(source, index)
+      const chunkStart = index * FILE_CHUNK_SIZE
+      controller.enqueue(chunk.subarray(
+        Math.max(start - chunkStart, 0),
+        Math.min(end + 1 - chunkStart, chunk.length),
+      ))
+
+      index++
+      if (index > lastIndex) {
+        controller.close()
+      }
+    },
+  })
+}
+
+/**
+ * @param {Source} source
+ * @param {number} index
+ * @returns {Promise<Uint8Array>}
+ */
+async function decryptChunk(source, index) {
+  const start = FILE_NONCE_SIZE + index * FILE_SEALED_CHUNK_SIZE
+  const end = Math.min(start + FILE_SEALED_CHUNK_SIZE, source.encryptedSize) - 1
+  const response = await fetchRange(source.url, start, end)
+  const sealed = await response.arrayBuffer()
+
+  const nonce = source.baseNonce.slice()
+  nonce[8] ^= (index >>> 24) & 0xff
+  nonce[9] ^= (index >>> 16) & 0xff
+  nonce[10] ^= (index >>> 8) & 0xff
+  nonce[11] ^= index & 0xff
+  if (index === source.chunks - 1) {
+    nonce[7] ^= 1
+  }
+
+  return new Uint8Array(await crypto.subtle.decrypt({name: 'AES-GCM', iv: nonce}, source.key, sealed))
+}
+
+/**
+ * @param {string} url
+ * @param {number} start
+ * @param {number} end included
+ * @returns {Promise<Response>}
+ */
+async function fetchRange(url, start, end) {
+  const response = await fetch(url, {headers: {Range: `bytes=${start}-${end}`}})
+  if (response.status !== 206) {
+    throw new Error(`failed to fetch a range of ${url}: ${response.status}`)
+  }
+  return response
+}
+
+/**
+ * @param {string} hex
+ * @returns {Uint8Array}
+ */
+function hexToBytes(hex) {
+  const bytes = new Uint8Array(hex.length / 2)
+  for (let i = 0; i < bytes.length; i++) {
+    bytes[i] = parseInt(hex.slice(2 * i, 2 * i + 2), 16)
+  }
+  return bytes
+}
@@ -1,4 +1,5 @@
 import * as VideoPlayer from "./video_player.mjs"
+import {t} from "./messages.mjs"
 
 
 window.playVideo = function (thumbnailEl) {
@@ -8,23 +9,343 @@ window.playVideo = function (thumbnailEl) {
   if (video) {
     VideoPlayer.play(`${baseUrl}/${video}`)
   } else {
-    const accessRule = thumbnailEl.dataset.accessRule
-    const accessIv = thumbnailEl.dataset.accessIv
-    const accessCiphertext = thumbnailEl.dataset.accessCiphertext
-    const accessSalt = thumbnailEl.dataset.accessSalt
-    const accessIterations = thumbnailEl.dataset.accessIterations
+    unlock(parseAccess(thumbnailEl.dataset.access)).then(payload => {
+      if (payload === null) {
+        return
+      }
+
+      revealVideo(thumbnailEl, payload)
+
+      if (payload.key) {
+        playEncryptedVideo(payload.key, `${baseUrl}/${payload.video}`)
+      } else {
+        VideoPlayer.play(`${baseUrl}/${payload.video}`)
+      }
+    }).catch(error => {
+      alert(t('wrong_code'))
+      console.error(error)
+    })
+  }
+}
+
+/**
+ * Play the videos of the playlist one after the other, starting from the one of `thumbnailEl`
+ * @param {HTMLElement} thumbnailEl
+ */
+window.playPlaylist = function (thumbnailEl) {
+  const thumbnailEls = Array.from(document.querySelectorAll('.playlist .video-thumbnail'))
+  const nextEl = thumbnailEls[thumbnailEls.indexOf(thumbnailEl) + 1]
+  VideoPlayer.onEnded(nextEl ? () => playPlaylist(nextEl) : null)
+  playVideo(thumbnailEl)
+}
+
+// Must match `rule_keys::ACCESS_FORMAT_VERSION` in the Rust code
+const ACCESS_FORMAT_VERSION = 1
+
+/**
+ * @typedef {{iv: string, ciphertext: string}} Encrypted
+ * @typedef {{rule: string, key: {salt: string, iterations: number, key: Encrypted}, payload: Encrypted}} RuleAccess
+ */
+
+/**
+ * @param {string} data
+ * @returns {RuleAccess[]}
+ */
+function parseAccess(data) {
+  const access = JSON.parse(data)
+  if (access.version !== ACCESS_FORMAT_VERSION) {
+    throw new Error(`unsupported access format ${access.version}`)
+  }
+  return access.rules
+}
+
+/**
+ * Decrypt the access payload of a video, trying the stored keys and codes of each of its rules
+ * first, and asking for a code otherwise
+ * @param {RuleAccess[]} access
+ * @returns {Promise<{video: string, key?: string}|null>} `null` if no code was entered
+ */
+async function unlock(access) {
+  const stored = await unlockWithStoredCodes(access)
+  if (stored !== null) {
+    return stored
+  }
 
-    const password = getPassword(accessRule)
+  const rules = access.map(each => each.rule).join(', ')
+  const password = prompt(t('video_code_prompt', rules))
+  if (!password) {
+    return null
+  }
+
+  for (const ruleAccess of access) {
+    try {
+      const payload = await unlockWithPassword(ruleAccess, password)
+      savePassword(ruleAccess.rule, password)
+      return payload
+    } catch (error) {
+      // Try the next rule
+    }
+  }
+
+  throw new Error('the code does not match any rule')
+}
+
+/**
+ * @param {RuleAccess[]} access
+ * @returns {Promise<{video: string, key?: string}|null>} `null` if no stored code works
+ */
+async function unlockWithStoredCodes(access) {
+  for (const ruleAccess of access) {
+    const {rule, key, payload} = ruleAccess
+    const storedKey = JSON.parse(localStorage.getItem(`contentKey:${rule}`) ?? 'null')
+    if (storedKey && storedKey.wrapped === key.key.ciphertext) {
+      try {
+        return await decryptPayload(storedKey.key, payload)
+      } catch (error) {
+        console.warn(`the stored key for ${rule} does not work`, error)
+      }
+    }
+
+    const password = localStorage.getItem(`password:${rule}`)
     if (password) {
-      decrypt(password, accessSalt, Number(accessIterations), accessIv, accessCiphertext).then(video => {
-        VideoPlayer.play(`${baseUrl}/${video}`)
-        savePassword(accessRule, password)
-      }).catch(error => {
-        alert("Code incorrect")
-        console.error(error)
+      try {
+        return await unlockWithPassword(ruleAccess, password)
+      } catch (error) {
+        console.warn(`the stored code for ${rule} does not work`, error)
+      }
+    }
+  }
+
+  return null
+}
+
+/**
+ * Unwrap the content key of the rule with the password, then decrypt the payload with it. The slow
+ * key derivation only runs once per rule: the content key is then stored
+ * @param {RuleAccess} ruleAccess
+ * @param {string} password
+ */
+async function unlockWithPassword({rule, key, payload}, password) {
+  const passwordKey = await deriveKey(password, key.salt, key.iterations)
+  const contentKey = await decryptText(passwordKey, key.key.iv, key.key.ciphertext)
+  localStorage.setItem(`contentKey:${rule}`, JSON.stringify({wrapped: key.key.ciphertext, key: contentKey}))
+  return await decryptPayload(contentKey, payload)
+}
+
+/**
+ * @param {string} contentKeyHex
+ * @param {Encrypted} payload
+ */
+async function decryptPayload(contentKeyHex, payload) {
+  const contentKey = await crypto.subtle.importKey(
+    "raw",
+    hexToArrayBuffer(contentKeyHex),
+    "AES-GCM",
+    false,
+    ["decrypt"],
+  )
+  return JSON.parse(await decryptText(contentKey, payload.iv, payload.ciphertext))
+}
+
+/**
+ * Show the thumbnail and the tags of a hidden video, once its payload is decrypted
+ * @param {HTMLElement} thumbnailEl
+ * @param {{thumbnail?: string, tags?: string[]}} payload
+ */
+function revealVideo(thumbnailEl, payload) {
+  const thumbnailsUrl = thumbnailEl.dataset.thumbnailsUrl
+  if (!thumbnailsUrl) {
+    return
+  }
+
+  const container = thumbnailEl.closest('.video-container')
+  if (payload.thumbnail) {
+    const thumbnailUrl = `${thumbnailsUrl}/${payload.thumbnail}`
+    thumbnailEl.querySelector('.video-thumbnail-img').src = thumbnailUrl
+    container.querySelector('.video-video').poster = thumbnailUrl
+  }
+
+  if (payload.tags) {
+    const tagsEl = container.querySelector('.video-tags')
+    tagsEl.replaceChildren(...payload.tags.map(tag => {
+      const tagEl = document.createElement('span')
+      tagEl.className = 'video-tag'
+      tagEl.textContent = tag
+      return tagEl
+    }))
+  }
+
+  delete thumbnailEl.dataset.thumbnailsUrl
+}
+
+/**
+ * Reveal the hidden videos that can be unlocked with the stored codes, without asking for any
+ */
+async function revealHiddenVideos() {
+  for (const thumbnailEl of document.querySelectorAll('.video-thumbnail[data-thumbnails-url]')) {
+    const payload = await unlockWithStoredCodes(parseAccess(thumbnailEl.dataset.access))
+    if (payload !== null) {
+      revealVideo(thumbnailEl, payload)
+    }
+  }
+}
+
+/**
+ * Store the code carried by an unlock link, like `#unlock=<rule>&code=<code>`, then remove it from
+ * the address bar. The fragment is never sent to the server
+ */
+function consumeUnlockFragment() {
+  const params = new URLSearchParams(window.location.hash.slice(1))
+  const rule = params.get('unlock')
+  const code = params.get('code')
+  if (rule === null || code === null) {
+    return
+  }
+
+  savePassword(rule, code)
+  history.replaceState(null, '', window.location.pathname + window.location.search)
+}
+
+/**
+ * Ask for the code of the collection once, then unlock all the videos of the page
+ * @param {HTMLElement} collectionEl
+ */
+window.unlockCollection = function (collectionEl) {
+  const rule = collectionEl.dataset.rule
+  const ruleAccess = findCollectionAccess(rule)
+  if (ruleAccess === null) {
+    return
+  }
+
+  const password = prompt(t('collection_code_prompt', rule))
+  if (!password) {
+    return
+  }
+
+  unlockWithPassword(ruleAccess, password).then(() => {
+    savePassword(rule, password)
+    showCollectionUnlocked(collectionEl)
+    return revealHiddenVideos()
+  }).catch(error => {
+    alert(t('wrong_code'))
+    console.error(error)
+  })
+}
+
+/**
+ * The access of the collection rule, taken from any video of the page
+ * @param {string} rule
+ * @returns {RuleAccess|null}
+ */
+function findCollectionAccess(rule) {
+  const thumbnailEl = document.querySelector('.video-thumbnail[data-access]')
+  if (!thumbnailEl) {
+    return null
+  }
+  return parseAccess(thumbnailEl.dataset.access).find(each => each.rule === rule) ?? null
+}
+
+function showCollectionUnlocked(collectionEl) {
+  collectionEl.querySelector('.collection-unlock-button').style.display = 'none'
+  collectionEl.querySelector('.collection-unlocked').style.display = ''
+}
+
+async function checkCollectionUnlocked() {
+  const collectionEl = document.querySelector('.collection-unlock')
+  if (!collectionEl) {
+    return
+  }
+
+  const ruleAccess = findCollectionAccess(collectionEl.dataset.rule)
+  if (ruleAccess !== null && await unlockWithStoredCodes([ruleAccess]) !== null) {
+    showCollectionUnlocked(collectionEl)
+  }
+}
+
+consumeUnlockFragment()
+revealHiddenVideos().catch(error => console.error(error))
+checkCollectionUnlocked().catch(error => console.error(error))
+
+// The root of the site, where `decrypt_worker.js` is published
+const SITE_ROOT = new URL('../', import.meta.url)
+
+/**
+ * The encrypted file and hex-encoded key of each video being played, by the file name the video
+ * element requests from the service worker
+ * @type {Map<string, {url: string, key: string}>}
+ */
+const decryptionSources = new Map()
+
+const decryptWorkerReady = registerDecryptWorker()
+
+/**
+ * Register the service worker that decrypts the videos while they are played
+ * @returns {Promise<boolean>} whether it controls the page
+ */
+async function registerDecryptWorker() {
+  if (!('serviceWorker' in navigator)) {
+    return false
+  }
+
+  navigator.serviceWorker.addEventListener('message', event => {
+    if (event.data?.type === 'decryption-source') {
+      event.ports[0].postMessage(decryptionSources.get(event.data.name) ?? null)
+    }
+  })
+
+  try {
+    await navigator.serviceWorker.register(new URL('decrypt_worker.js', SITE_ROOT), {scope: SITE_ROOT.href})
+    await navigator.serviceWorker.ready
+    if (!navigator.serviceWorker.controller) {
+      // A newly installed worker claims the page shortly after, unless it was force-reloaded
+      await new Promise(resolve => {
+        navigator.serviceWorker.addEventListener('controllerchange', resolve, {once: true})
+        setTimeout(resolve, 1000)
       })
     }
+  } catch (error) {
+    console.error('failed to register the decryption service worker', error)
   }
+
+  return navigator.serviceWorker.controller !== null
+}
+
+let lastObjectUrl = null
+
+function playEncryptedVideo(keyHex, url) {
+  const signal = VideoPlayer.open(new URL(url, window.location).toString())
+  decryptWorkerReady.then(async isStreamed => {
+    if (isStreamed) {
+      // The service worker decrypts the ranges that the video element requests
+      const name = url.split('/').pop().replace(/\.enc$/, '')
+      decryptionSources.set(name, {url: new URL(url, window.location).href, key: keyHex})
+      signal.throwIfAborted()
+      VideoPlayer.load(new URL(`decrypted/${name}`, SITE_ROOT).href)
+      return
+    }
+
+    // Without a service worker, like in private browsing, the whole file is decrypted first
+    const key = await crypto.subtle.importKey(
+      "raw",
+      hexToArrayBuffer(keyHex),
+      "AES-GCM",
+      false,
+      ["decrypt"],
+    )
+    const blob = await decryptFile(key, url, signal)
+    if (lastObjectUrl) {
+      URL.revokeObjectURL(lastObjectUrl)
+    }
+    lastObjectUrl = URL.createObjectURL(blob)
+    VideoPlayer.load(lastObjectUrl)
+  }).catch(error => {
+    if (signal.aborted) {
+      // The player was closed in the meantime
+      return
+    }
+    VideoPlayer.showError()
+    console.error(error)
+  })
 }
 
 window.copyShareLink = function (el) {
@@ -37,32 +358,23 @@ window.copyShareLink = function (el) {
   })
 }
 
-function getPassword(rule) {
-  const stored = localStorage.getItem(`password:${rule}`)
-  if (stored) {
-    return stored
-  }
-
-  return prompt(`Cette video fait partie de la collection ${rule} et est protégée. Merci d'entrer le code d'accès`)
-}
-
 function savePassword(rule, password) {
   localStorage.setItem(`password:${rule}`, password)
 }
 
-async function decrypt(password, salt, iterations, iv, ciphertext) {
-  function stringToBytes(str) {
-    return new TextEncoder().encode(str)
-  }
+function stringToBytes(str) {
+  return new TextEncoder().encode(str)
+}
 
-  function hexToArrayBuffer(hex) {
-    const bytes = []
-    for (let i = 0; i < hex.length; i += 2) {
-      bytes.push(parseInt(hex.slice(i, i + 2), 16))
-    }
-    return new Uint8Array(bytes)
+function hexToArrayBuffer(hex) {
+  const bytes = []
+  for (let i = 0; i < hex.length; i += 2) {
+    bytes.push(parseInt(hex.slice(i, i + 2), 16))
   }
+  return new Uint8Array(bytes)
+}
 
+async function deriveKey(password, salt, iterations) {
   const passwordAsKey = await crypto.subtle.importKey(
     "raw",
     stringToBytes(password),
@@ -71,7 +383,7 @@ async function decrypt(password, salt, iterations, iv, ciphertext) {
     ["deriveKey"],
   )
 
-  const key = await crypto.subtle.deriveKey(
+  return await crypto.subtle.deriveKey(
     {
       name: "PBKDF2",
       salt: stringToBytes(salt),
@@ -83,7 +395,9 @@ async function decrypt(password, salt, iterations, iv, ciphertext) {
     false,
     ["decrypt"],
   )
+}
 
+async function decryptText(key, iv, ciphertext) {
   const plaintextBytes = await crypto.subtle.decrypt({
     name: "AES-GCM",
     iv: hexToArrayBuffer(iv)
@@ -92,79 +406,256 @@ async function decrypt(password, salt, iterations, iv, ciphertext) {
   return new TextDecoder().decode(plaintextBytes)
 }
 
+// Must match the format produced by `encrypt::encrypt_stream` in the Rust code
+const FILE_NONCE_SIZE = 12
+const FILE_SEALED_CHUNK_SIZE = 1024 * 1024 + 16
+
+/**
+ * Download and decrypt a file, chunk by chunk, as it arrives. The whole file is kept in memory and
+ * only returned once it is complete, so this is only used when `decrypt_worker.js` is not available.
+ * @param {CryptoKey} key
+ * @param {string} url
+ * @param {AbortSignal} signal stops the download and the decryption
+ * @returns {Promise<Blob>}
+ */
+async function decryptFile(key, url, signal) {
+  const response = await fetch(url, {signal})
+  if (!response.ok) {
+    throw new Error(`failed to fetch ${url}: ${response.status}`)
+  }
+
+  const reader = response.body.getReader()
+  const plaintextChunks = []
+  let buffer = new Uint8Array(0)
+  let baseNonce = null
+
+  async function decryptChunk(chunk, isLast) {
+    const nonce = baseNonce.slice()
+    const index = plaintextChunks.length
+    nonce[8] ^= (index >>> 24) & 0xff
+    nonce[9] ^= (index >>> 16) & 0xff
+    nonce[10] ^= (index >>> 8) & 0xff
+    nonce[11] ^= index & 0xff
+    if (isLast) {
+      nonce[7] ^= 1
+    }
+
+    signal.throwIfAborted()
+    plaintextChunks.push(await crypto.subtle.decrypt({name: "AES-GCM", iv: nonce}, key, chunk))
+  }
+
+  while (true) {
+    const {done, value} = await reader.read()
+    if (done) {
+      break
+    }
+
+    const newBuffer = new Uint8Array(buffer.length + value.length)
+    newBuffer.set(buffer)
+    newBuffer.set(value, buffer.length)
+    buffer = newBuffer
+
+    if (baseNonce === null && buffer.length >= FILE_NONCE_SIZE) {
+      baseNonce = buffer.slice(0, FILE_NONCE_SIZE)
+      buffer = buffer.slice(FILE_NONCE_SIZE)
+    }
+
+    // A chunk is only known not to be the last one when more data follows it
+    while (baseNonce !== null && buffer.length > FILE_SEALED_CHUNK_SIZE) {
+      await decryptChunk(buffer.slice(0, FILE_SEALED_CHUNK_SIZE), false)
+      buffer = buffer.slice(FILE_SEALED_CHUNK_SIZE)
+    }
+  }
+
+  if (baseNonce === null) {
+    throw new Error('truncated encrypted file')
+  }
+  await decryptChunk(buffer, true)
+
+  return new Blob(plaintextChunks, {type: url.includes('.mp4') ? 'video/mp4' : ''})
+}
+
+/** The maximum number of tags suggested by the search */
+const MAX_TAG_RESULTS = 3
+/** The maximum number of videos found by the search */
+const MAX_VIDEO_RESULTS = 12
+/** The fraction of the trigrams of a searched word that an entry must have to match it */
+const MIN_WORD_SCORE = 0.5
+
+/** The maximum number of videos shown by the filter page */
+const MAX_FILTER_RESULTS = 120
+
+/** @type {Promise<object>|null} */
 let searchIndex = null
 
-window.runSearch = function (text) {
-  const tagIndexes = new Set(search(text))
+window.runSearch = async function (input) {
+  const baseUrl = input.dataset.baseUrl
+  const localeUrl = input.dataset.localeUrl
+  const text = input.value
+  const index = await loadSearchIndex(baseUrl)
+  if (input.value !== text) {
+    // A newer search is running
+    return
+  }
+
+  const words = normalize(text, index.folding).split(' ').filter(word => word.length > 0)
+  const tags = rankSearch(index.tag_trigrams, words, false).slice(0, MAX_TAG_RESULTS)
+  const videos = rankSearch(index.video_trigrams, words, true).slice(0, MAX_VIDEO_RESULTS)
+
+  const tagsEl = document.getElementById('search-results')
+  tagsEl.replaceChildren(...tags.map(tagIndex => {
+    const tag = index.tags[tagIndex]
+    const tagEl = document.createElement('a')
+    tagEl.className = 'video-tag'
+    tagEl.href = `${localeUrl}/tag/${tag.clean_name}.html`
+    tagEl.textContent = tag.name
+    return tagEl
+  }))
 
-  const tags = document.getElementById('search-results').children
-  for (let i = 0; i < tags.length; i++) {
-    tags[i].style.display = tagIndexes.has(i) ? '' : 'none'
+  const videosEl = document.getElementById('search-videos')
+  videosEl.replaceChildren(...videos.map(videoIndex => searchVideoElement(baseUrl, localeUrl, index.videos[videoIndex])))
+}
+
+/**
+ * Narrow the videos to those with all the checked facet values, and update the count of each value
+ * among them
+ */
+window.runFilter = async function () {
+  const pageEl = document.getElementById('filter-page')
+  const baseUrl = pageEl.dataset.baseUrl
+  const localeUrl = pageEl.dataset.localeUrl
+  const index = await loadSearchIndex(baseUrl)
+  const checkboxes = Array.from(pageEl.querySelectorAll('input[type=checkbox]'))
+  const facetVideos = checkbox => index.facets[checkbox.dataset.facet].values[checkbox.dataset.value].videos
+
+  /** @type {Set<number>|null} */
+  let selected = null
+  for (const checkbox of checkboxes) {
+    if (checkbox.checked) {
+      const videos = facetVideos(checkbox)
+      selected = new Set(selected === null ? videos : videos.filter(video => selected.has(video)))
+    }
   }
+
+  for (const checkbox of checkboxes) {
+    const videos = facetVideos(checkbox)
+    const count = selected === null ? videos.length : videos.filter(video => selected.has(video)).length
+    const valueEl = checkbox.parentElement
+    valueEl.querySelector('.filter-count').textContent = String(count)
+    valueEl.style.display = count > 0 || checkbox.checked ? '' : 'none'
+  }
+
+  const videos = selected === null ? [] : Array.from(selected).sort((a, b) => a - b)
+  document.getElementById('filter-summary').textContent = selected === null ? '' :
+    videos.length > MAX_FILTER_RESULTS ? t('filter_truncated', videos.length, MAX_FILTER_RESULTS) :
+      t('filter_count', videos.length)
+  document.getElementById('filter-videos').replaceChildren(
+    ...videos.slice(0, MAX_FILTER_RESULTS).map(videoIndex => searchVideoElement(baseUrl, localeUrl, index.videos[videoIndex]))
+  )
 }
 
 /**
- * @param {string} text
- * @param {number} maxResults
- * @returns {number[]}
+ * A link to the page of a video of the search index, with its thumbnail and title
+ * @param {string} baseUrl the relative URL of the root of the site
+ * @param {string} localeUrl the relative URL of the root of the pages of the locale
+ * @param {object} video
+ * @returns {HTMLElement}
  */
-function search(text, maxResults = 3) {
+function searchVideoElement(baseUrl, localeUrl, video) {
+  const videoEl = document.createElement('a')
+  videoEl.className = 'search-video'
+  videoEl.href = `${localeUrl}/video/${video.short_name}.html`
+
+  const thumbnailEl = document.createElement('img')
+  thumbnailEl.src = `${baseUrl}/${video.thumbnail}`
+  thumbnailEl.alt = t('thumbnail_alt')
+  thumbnailEl.loading = 'lazy'
+
+  const titleEl = document.createElement('span')
+  titleEl.textContent = video.title
+
+  videoEl.append(thumbnailEl, titleEl)
+  return videoEl
+}
+
+/**
+ * @param {string} baseUrl
+ * @returns {Promise<object>}
+ */
+function loadSearchIndex(baseUrl) {
   if (searchIndex === null) {
-    searchIndex = buildSearchIndex()
+    searchIndex = fetch(`${baseUrl}/search_index.json`, {cache: 'no-cache'}).then(response => {
+      if (!response.ok) {
+        throw new Error(`failed to load the search index: ${response.status}`)
+      }
+      return response.json()
+    })
+    searchIndex.catch(() => {
+      searchIndex = null
+    })
   }
+  return searchIndex
+}
 
+/**
+ * Rank the entries of a trigram index by how well they match the searched words. Each word scores
+ * the fraction of its trigrams found in the entry.
+ * @param {Object<string, number[]>} trigramIndex
+ * @param {string[]} words
+ * @param {boolean} matchAllWords whether to only keep the entries that match every word
+ * @returns {number[]}
+ */
+function rankSearch(trigramIndex, words, matchAllWords) {
   const scores = new Map()
-  for (const trigram of getTrigrams(normalize(text))) {
-    const indexes = searchIndex.get(trigram)
-    if (indexes) {
+  const matchedWords = new Map()
+
+  for (const word of words) {
+    const trigrams = getTrigrams(word)
+    const counts = new Map()
+    for (const trigram of trigrams) {
+      const indexes = Object.hasOwn(trigramIndex, trigram) ? trigramIndex[trigram] : []
       for (const index of indexes) {
-        scores.set(index, (scores.get(index) ?? 0) + 1)
+        counts.set(index, (counts.get(index) ?? 0) + 1)
+      }
+    }
+
+    for (const [index, count] of counts) {
+      const score = count / trigrams.length
+      if (score >= MIN_WORD_SCORE) {
+        scores.set(index, (scores.get(index) ?? 0) + score)
+        matchedWords.set(index, (matchedWords.get(index) ?? 0) + 1)
       }
     }
   }
 
   return (
     Array.from(scores.entries())
-      .sort((a, b) => b[1] - a[1])
-      .slice(0, maxResults)
+      .filter(([index]) => !matchAllWords || matchedWords.get(index) === words.length)
+      // On ties, keep the order of the index, where videos go from the most recent
+      .sort((a, b) => b[1] - a[1] || a[0] - b[0])
       .map(each => each[0])
   )
 }
 
-function buildSearchIndex() {
-  const tags = document.getElementById('search-results').children
-  const indexesByTrigram = new Map()
-
-  for (let i = 0; i < tags.length; i++) {
-    for (let trigram of getTrigrams(normalize(tags[i].textContent))) {
-      if (!indexesByTrigram.has(trigram)) {
-        indexesByTrigram.set(trigram, [i])
-      } else {
-        indexesByTrigram.get(trigram).push(i)
-      }
-    }
-  }
-
-  return indexesByTrigram
-}
-
 /**
+ * The trigrams of each normalized word, or the word itself when it is shorter, like `trigrams()` in
+ * `search_index.rs`
  * @param {string} text
  * @returns {string[]}
  */
 function getTrigrams(text) {
-  if (text.length === 0) {
-    return []
-  }
-  
   const trigrams = []
 
   for (const word of text.split(' ')) {
+    if (word.length === 0) {
+      continue
+    }
+
     if (word.length < 3) {
       trigrams.push(word)
     } else {
-      for (let j = 0; j < text.length - 2; j++) {
-        trigrams.push(text.slice(j, j + 3))
+      for (let j = 0; j < word.length - 2; j++) {
+        trigrams.push(word.slice(j, j + 3))
       }
     }
   }
@@ -173,10 +664,14 @@ function getTrigrams(text) {
 }
 
 /**
- * Lower case and remove all especial diacritics from the text
+ * Lower case the text and remove the diacritics and punctuation, like `normalize()` in
+ * `search_index.rs`. The characters of `folding` are replaced first, so that `œ` becomes `oe` like
+ * in the index instead of being dropped.
  * @param {string} text
+ * @param {Object<string, string>} folding
  * @returns {string}
  */
-function normalize(text) {
-  return text.normalize('NFD').toLowerCase().replace(/[^0-9a-z ]/g, '')
+function normalize(text, folding) {
+  const folded = Array.from(text, c => folding[c] ?? c).join('')
+  return folded.normalize('NFD').toLowerCase().replace(/\s/g, ' ').replace(/[^0-9a-z ]/g, '')
 }
\ No newline at end of file
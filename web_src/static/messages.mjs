@@ -0,0 +1,13 @@
+/** The messages of the locale of the page, embedded by the `head` partial */
+const messages = JSON.parse(document.getElementById('messages')?.textContent ?? '{}')
+
+/**
+ * A message of the locale of the page, with its `{0}`, `{1}`... arguments replaced
+ * @param {string} key
+ * @param {...*} args
+ * @returns {string}
+ */
+export function t(key, ...args) {
+  const message = messages[key] ?? key
+  return message.replace(/\{(\d+)}/g, (match, index) => index < args.length ? String(args[index]) : match)
+}
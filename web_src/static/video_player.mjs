@@ -1,3 +1,5 @@
+import {t} from "./messages.mjs"
+
 const pageEl = document.createElement('div')
 pageEl.style.display = 'none'
 
@@ -26,17 +28,17 @@ shadowRoot.innerHTML = `
   </div>
 </div>
 <div id="help-modal" style="display: none">
-  <h1>Aide</h1>
-  <h2>Boutons</h2>
-  <p><img src="/static/video_player/add_favorite.svg"> Ajouter un point de repère</p>
-  <p><img src="/static/video_player/close.svg"> Fermer</p>
-  <p><img src="/static/video_player/pause.svg"> Pause <img src="/static/video_player/play.svg"> Lecture</p>
-  <h2>Gestes</h2>
-  <p><img src="/static/video_player/tap.svg"> Tape pour pause ou lecture</p>
-  <p><img src="/static/video_player/double_tag.svg"> Tape deux fois pour ajouter un point de repère</p>
-  <p><img src="/static/video_player/long_press.svg"> Maintiens le doigt pour ralentir</p>
-  <p><img src="/static/video_player/swipe_left.svg"> Glisse à gauche pour aller au repère précédent</p>
-  <p><img src="/static/video_player/swipe_right.svg"> Glisse à droite pour aller au repère suivant</p>
+  <h1>${t('player.help')}</h1>
+  <h2>${t('player.buttons')}</h2>
+  <p><img src="/static/video_player/add_favorite.svg"> ${t('player.add_favorite')}</p>
+  <p><img src="/static/video_player/close.svg"> ${t('player.close')}</p>
+  <p><img src="/static/video_player/pause.svg"> ${t('player.pause')} <img src="/static/video_player/play.svg"> ${t('player.play')}</p>
+  <h2>${t('player.gestures')}</h2>
+  <p><img src="/static/video_player/tap.svg"> ${t('player.tap')}</p>
+  <p><img src="/static/video_player/double_tag.svg"> ${t('player.double_tap')}</p>
+  <p><img src="/static/video_player/long_press.svg"> ${t('player.long_press')}</p>
+  <p><img src="/static/video_player/swipe_left.svg"> ${t('player.swipe_left')}</p>
+  <p><img src="/static/video_player/swipe_right.svg"> ${t('player.swipe_right')}</p>
   <div id="help-close"><img src="/static/video_player/close.svg"></div>
 </div>
 <div id="console"></div>
@@ -295,8 +297,7 @@ function renderFavorites() {
 }
 
 function loadFavorites() {
-  const src = videoEl.src
-  const favoritesStr = localStorage.getItem(`video_player:favorites:${src}`)
+  const favoritesStr = localStorage.getItem(`video_player:favorites:${currentSourceKey}`)
   if (favoritesStr) {
     favorites = JSON.parse(favoritesStr)
   } else {
@@ -305,19 +306,20 @@ function loadFavorites() {
 }
 
 function saveFavorites() {
-  const src = videoEl.src
-  localStorage.setItem(`video_player:favorites:${src}`, JSON.stringify(favorites))
+  localStorage.setItem(`video_player:favorites:${currentSourceKey}`, JSON.stringify(favorites))
 }
 
 // Timeline movement
 const knobEl = shadowRoot.getElementById('timeline-knob')
 const lastTimeBySource = new Map()
 let isSeeking = false
+// Identifies the current video, even when it is played from a temporary object URL
+let currentSourceKey = null
 
 function updateKnobPosition() {
   if (!isSeeking) {
     if (Number.isFinite(videoEl.currentTime) && Number.isFinite(videoEl.duration)) {
-      lastTimeBySource.set(videoEl.src, videoEl.currentTime)
+      lastTimeBySource.set(currentSourceKey, videoEl.currentTime)
       knobEl.style.left = `${videoEl.currentTime / videoEl.duration * 100}%`
     } else {
       knobEl.style.left = '0'
@@ -387,6 +389,7 @@ const closeEl = shadowRoot.getElementById('close')
 closeEl.addEventListener('click', () => {
   videoEl.pause()
   pageEl.style.display = 'none'
+  abortOpening()
 
   if (document.fullscreenElement) {
     document.exitFullscreen()
@@ -617,24 +620,82 @@ videoEl.addEventListener('emptied', () => {
   errorEl.style.display = 'none'
 })
 
+// End of the video
+let endedCallback = null
+videoEl.addEventListener('ended', () => {
+  // Start over the next time the video is opened
+  lastTimeBySource.delete(currentSourceKey)
+  if (endedCallback) {
+    endedCallback()
+  }
+})
+
 let lastScrollPosition = null
 pageEl.addEventListener('fullscreenchange', () => {
   if (!document.fullscreenElement && lastScrollPosition !== null) {
     // Exiting fullscreen
     videoEl.pause()
     pageEl.style.display = 'none'
+    abortOpening()
     document.body.scrollTo({top: lastScrollPosition, behavior: 'instant'})
   }
 })
 
+// Aborted when the opened video is closed or replaced, to stop preparing its source
+let openingController = null
+
+function abortOpening() {
+  if (openingController) {
+    openingController.abort()
+    openingController = null
+  }
+}
+
 export function play(src) {
+  open(new URL(src, window.location).toString())
+  load(src)
+}
+
+/**
+ * Show the player in the loading state, before the video source is available
+ * @param {string} sourceKey identifies the video, to remember its favorites and last position
+ * @returns {AbortSignal} aborted when the player is closed or opens another video
+ */
+export function open(sourceKey) {
+  abortOpening()
+  openingController = new AbortController()
   lastScrollPosition = document.body.scrollTop
   pageEl.style.display = 'block'
-  videoEl.src = src
-  videoEl.currentTime = lastTimeBySource.get(videoEl.src) || 0
-  videoEl.play()
+  currentSourceKey = sourceKey
+  videoEl.removeAttribute('src')
+  videoEl.load()
+  loadingEl.style.display = ''
 
   loadFavorites()
   renderFavorites()
   pageEl.requestFullscreen({navigationUI: 'show'})
+  return openingController.signal
+}
+
+/**
+ * Start playing the video opened with `open()`
+ * @param {string} src
+ */
+export function load(src) {
+  videoEl.src = src
+  videoEl.currentTime = lastTimeBySource.get(currentSourceKey) || 0
+  videoEl.play()
+}
+
+export function showError() {
+  loadingEl.style.display = 'none'
+  errorEl.style.display = ''
+}
+
+/**
+ * Call `callback` each time a video ends, like to play the next one of a playlist
+ * @param {(() => void) | null} callback
+ */
+export function onEnded(callback) {
+  endedCallback = callback
 }